@@ -0,0 +1,113 @@
+// Compares the boxed ast::Expr with the arena::Arena on a large expression.
+// Run with `cargo bench`.
+//
+// The arena is lowered from the boxed tree, so its time includes parsing into boxes,
+// and while lowering both trees are in memory. Its size alone is what the arena would
+// take once the parser builds it directly.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use tlox::arena::Arena;
+use tlox::ast::Expr;
+use tlox::parser::Parser;
+use tlox::scanner::{ErrorHandler, Scanner, Token};
+
+// Counts the bytes currently allocated on the heap.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// The terms are elements of a list, a chain of binary operators as long would be
+// nested too deeply for the parser.
+const TERMS: usize = 10_000;
+const RUNS: u32 = 20;
+
+fn source() -> String {
+    let terms: Vec<String> = (0..TERMS).map(|i| format!("(-{i} * \"s{i}\")")).collect();
+    format!("[{}]", terms.join(", "))
+}
+
+fn scan(source: &str) -> Vec<Token> {
+    let mut err_handler = ErrorHandler::new();
+    let mut scanner = Scanner::new(source, &mut err_handler);
+    scanner.scan_tokens();
+    scanner.tokens
+}
+
+fn parse(tokens: Vec<Token>) -> Expr {
+    Parser::new(tokens, &mut ErrorHandler::new())
+        .parse()
+        .unwrap()
+}
+
+fn lower(expr: &Expr) -> Arena {
+    let mut arena = Arena::new();
+    arena.lower_expr(expr);
+    arena
+}
+
+// Average time of `f` over RUNS runs, `setup` isn't timed.
+fn time<S, T, R>(
+    mut setup: impl FnMut() -> S,
+    mut f: impl FnMut(S) -> T,
+    drop_result: R,
+) -> Duration
+where
+    R: Fn(T),
+{
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let input = setup();
+        let start = Instant::now();
+        let result = f(input);
+        total += start.elapsed();
+        drop_result(result);
+    }
+    total / RUNS
+}
+
+// Heap bytes still in use after `f` returns, i.e. the size of what it built.
+fn memory<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let result = f();
+    let after = ALLOCATED.load(Ordering::Relaxed);
+    (result, after - before)
+}
+
+fn main() {
+    let source = source();
+    let tokens = scan(&source);
+    println!(
+        "{TERMS} terms, {} tokens, average of {RUNS} runs",
+        tokens.len()
+    );
+
+    let parse_time = time(|| tokens.clone(), parse, drop);
+    let (expr, boxed_bytes) = memory(|| parse(tokens.clone()));
+    let lower_time = time(|| (), |_| lower(&expr), drop);
+    let (arena, arena_bytes) = memory(|| lower(&expr));
+
+    println!("boxed: parse {parse_time:?}, {boxed_bytes} bytes");
+    println!(
+        "arena: parse + lower {:?}, {arena_bytes} bytes for {} nodes, {} bytes at the peak",
+        parse_time + lower_time,
+        arena.expr_count(),
+        boxed_bytes + arena_bytes
+    );
+}
@@ -0,0 +1,160 @@
+// Arena-allocated version of ast::Expr.
+//
+// Nodes live in one Vec and refer to each other by ExprId instead of Box,
+// tokens and literal values are stored once in the arena instead of in every node.
+// Passes that need to attach data to nodes (resolver depths, types, ...)
+// keep it in a SideTable keyed by ExprId instead of adding fields to the AST.
+// Spans are such a side table which is always filled.
+//
+// The nodes, Arena and Arena::lower_expr are generated from src/grammar.ast into nodes.rs,
+// like ast.rs. Nothing uses the arena yet: the parser still builds the boxed tree and
+// lower_expr copies it, see todo.md for making the arena the AST.
+
+use crate::ast::Span;
+
+mod nodes;
+
+pub use nodes::*;
+
+// Data attached to some of the nodes of an Arena.
+pub struct SideTable<T> {
+    values: Vec<Option<T>>,
+}
+
+fn next_id<T>(items: &[T]) -> u32 {
+    u32::try_from(items.len()).expect("Too many nodes for the arena.")
+}
+
+// Smallest span covering the parts of a node, added in any order.
+#[derive(Default)]
+struct Covering(Option<Span>);
+
+impl Covering {
+    fn add(&mut self, span: Span) {
+        let covering = self.0.unwrap_or(span);
+        let (line, col) = (covering.line, covering.col).min((span.line, span.col));
+        let (end_line, end_col) =
+            (covering.end_line, covering.end_col).max((span.end_line, span.end_col));
+        self.0 = Some(Span {
+            line,
+            col,
+            end_line,
+            end_col,
+        });
+    }
+    fn span(self) -> Span {
+        self.0.expect("Nodes have a token, a span or a child.")
+    }
+}
+
+impl<T> SideTable<T> {
+    pub fn new() -> Self {
+        Self { values: Vec::new() }
+    }
+    // Avoids reallocations when most of the nodes will get a value.
+    pub fn for_arena(arena: &Arena) -> Self {
+        let mut values = Vec::new();
+        values.resize_with(arena.expr_count(), || None);
+        Self { values }
+    }
+    pub fn insert(&mut self, id: ExprId, value: T) -> Option<T> {
+        if id.index() >= self.values.len() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        self.values[id.index()].replace(value)
+    }
+    pub fn get(&self, id: ExprId) -> Option<&T> {
+        self.values.get(id.index()).and_then(Option::as_ref)
+    }
+    pub fn remove(&mut self, id: ExprId) -> Option<T> {
+        self.values.get_mut(id.index()).and_then(Option::take)
+    }
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Expr;
+    use crate::scanner::ErrorHandler;
+
+    fn parse(source: &str) -> Expr {
+        crate::parse(source, &mut ErrorHandler::new()).unwrap()
+    }
+
+    #[test]
+    fn test_lower_keeps_tree_and_spans() {
+        let expr = parse("-1 * (2 + \"three\") == 4 ? 5 : 6");
+        let mut arena = Arena::new();
+        let root = arena.lower_expr(&expr);
+        assert_eq!(arena.display_expr(root).to_string(), expr.to_string());
+        assert_eq!(arena.expr_span(root), expr.span());
+        assert_eq!(arena.expr_count(), 12);
+        // Children are allocated before their parents.
+        assert_eq!(root, arena.expr_ids().last().unwrap());
+    }
+
+    #[test]
+    fn test_lower_lists() {
+        let expr = parse("[[], [1, 2], xs[0] = 3, {\"a\": {}}, ++ys[0], ys[1]--][1][0]");
+        let mut arena = Arena::new();
+        let root = arena.lower_expr(&expr);
+        assert_eq!(arena.display_expr(root).to_string(), expr.to_string());
+        assert_eq!(arena.expr_span(root), expr.span());
+        let ArenaExpr::Index { object, .. } = arena.expr(root) else {
+            panic!("Expected an index, got {:?}", arena.expr(root));
+        };
+        let ArenaExpr::Index { object, .. } = arena.expr(object) else {
+            panic!("Expected an index, got {:?}", arena.expr(object));
+        };
+        let ArenaExpr::List { elements } = arena.expr(object) else {
+            panic!("Expected a list, got {:?}", arena.expr(object));
+        };
+        assert_eq!(arena.expr_list(elements).len(), 6);
+    }
+
+    #[test]
+    fn test_lowered_spans() {
+        for source in [
+            "++xs[0]",
+            "xs[0]++",
+            "xs[0] += 1",
+            "(1 +\n 2)",
+            "a ? \"b\n\" : c",
+        ] {
+            let expr = parse(source);
+            let mut arena = Arena::new();
+            let root = arena.lower_expr(&expr);
+            for id in arena.expr_ids() {
+                let span = arena.expr_span(id);
+                assert!(
+                    (span.line, span.col) < (span.end_line, span.end_col),
+                    "{source}"
+                );
+            }
+            assert_eq!(arena.expr_span(root), expr.span(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_side_table() {
+        let mut arena = Arena::new();
+        let root = arena.lower_expr(&parse("1 + 2"));
+        let mut depths: SideTable<usize> = SideTable::for_arena(&arena);
+        assert_eq!(depths.get(root), None);
+        assert_eq!(depths.insert(root, 1), None);
+        assert_eq!(depths.insert(root, 2), Some(1));
+        assert_eq!(depths.get(root), Some(&2));
+        assert_eq!(depths.remove(root), Some(2));
+        assert_eq!(depths.get(root), None);
+        // Tables grow on demand.
+        let mut types: SideTable<&str> = SideTable::new();
+        types.insert(root, "number");
+        assert_eq!(types.get(root), Some(&"number"));
+    }
+}
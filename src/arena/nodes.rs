@@ -0,0 +1,371 @@
+// Generated by generate_ast from src/grammar.ast, do not edit by hand.
+
+use core::fmt;
+
+use crate::scanner::{LiteralType, Token};
+use crate::span::Span;
+
+use super::{next_id, Covering};
+use crate::ast::Expr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// Children of a node with any number of them, e.g. list elements or map entries.
+// They are kept next to each other in the arena so that nodes stay Copy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExprList {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenId(u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LiteralTypeId(u32);
+
+// Same variants as Expr, spans are kept in the arena.
+#[derive(Copy, Clone, Debug)]
+pub enum ArenaExpr {
+    Binary {
+        left: ExprId,
+        operator: TokenId,
+        right: ExprId,
+    },
+    Conditional {
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    },
+    Grouping {
+        expression: ExprId,
+    },
+    Index {
+        object: ExprId,
+        bracket: TokenId,
+        index: ExprId,
+    },
+    IndexSet {
+        object: ExprId,
+        bracket: TokenId,
+        index: ExprId,
+        operator: TokenId,
+        value: ExprId,
+    },
+    List {
+        elements: ExprList,
+    },
+    Literal {
+        value: LiteralTypeId,
+    },
+    Map {
+        entries: ExprList,
+    },
+    Unary {
+        operator: TokenId,
+        right: ExprId,
+    },
+    Variable {
+        name: TokenId,
+    },
+}
+
+// Nodes of every kind with their spans, and the data they refer to.
+#[derive(Default)]
+pub struct Arena {
+    exprs: Vec<ArenaExpr>,
+    expr_spans: Vec<Span>,
+    expr_lists: Vec<ExprId>,
+    tokens: Vec<Token>,
+    literal_types: Vec<LiteralType>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn alloc_expr(&mut self, node: ArenaExpr, span: Span) -> ExprId {
+        let id = ExprId(next_id(&self.exprs));
+        self.exprs.push(node);
+        self.expr_spans.push(span);
+        id
+    }
+    pub fn expr(&self, id: ExprId) -> ArenaExpr {
+        self.exprs[id.index()]
+    }
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self.expr_spans[id.index()]
+    }
+    pub fn expr_count(&self) -> usize {
+        self.exprs.len()
+    }
+    // Ids of all the nodes, children come before their parents.
+    pub fn expr_ids(&self) -> impl Iterator<Item = ExprId> {
+        (0..next_id(&self.exprs)).map(ExprId)
+    }
+    pub fn add_expr_list(&mut self, ids: &[ExprId]) -> ExprList {
+        let start = next_id(&self.expr_lists);
+        self.expr_lists.extend_from_slice(ids);
+        ExprList {
+            start,
+            len: next_id(ids),
+        }
+    }
+    pub fn expr_list(&self, list: ExprList) -> &[ExprId] {
+        let start = list.start as usize;
+        &self.expr_lists[start..start + list.len as usize]
+    }
+    pub fn add_token(&mut self, token: Token) -> TokenId {
+        let id = TokenId(next_id(&self.tokens));
+        self.tokens.push(token);
+        id
+    }
+    pub fn token(&self, id: TokenId) -> &Token {
+        &self.tokens[id.0 as usize]
+    }
+    pub fn add_literal_type(&mut self, literal_type: LiteralType) -> LiteralTypeId {
+        let id = LiteralTypeId(next_id(&self.literal_types));
+        self.literal_types.push(literal_type);
+        id
+    }
+    pub fn literal_type(&self, id: LiteralTypeId) -> &LiteralType {
+        &self.literal_types[id.0 as usize]
+    }
+
+    // Copies the boxed tree into the arena, returns the id of its root. The span of a node
+    // covers its tokens, its spans and its children.
+    pub fn lower_expr(&mut self, expr: &Expr) -> ExprId {
+        let mut covering = Covering::default();
+        let node = match expr {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.lower_expr(left);
+                covering.add(self.expr_span(left));
+                covering.add(Span::from_token(operator));
+                let operator = self.add_token(operator.clone());
+                let right = self.lower_expr(right);
+                covering.add(self.expr_span(right));
+                ArenaExpr::Binary {
+                    left,
+                    operator,
+                    right,
+                }
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.lower_expr(condition);
+                covering.add(self.expr_span(condition));
+                let then_branch = self.lower_expr(then_branch);
+                covering.add(self.expr_span(then_branch));
+                let else_branch = self.lower_expr(else_branch);
+                covering.add(self.expr_span(else_branch));
+                ArenaExpr::Conditional {
+                    condition,
+                    then_branch,
+                    else_branch,
+                }
+            }
+            Expr::Grouping { expression, span } => {
+                let expression = self.lower_expr(expression);
+                covering.add(self.expr_span(expression));
+                covering.add(*span);
+                ArenaExpr::Grouping { expression }
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = self.lower_expr(object);
+                covering.add(self.expr_span(object));
+                covering.add(Span::from_token(bracket));
+                let bracket = self.add_token(bracket.clone());
+                let index = self.lower_expr(index);
+                covering.add(self.expr_span(index));
+                ArenaExpr::Index {
+                    object,
+                    bracket,
+                    index,
+                }
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                operator,
+                value,
+            } => {
+                let object = self.lower_expr(object);
+                covering.add(self.expr_span(object));
+                covering.add(Span::from_token(bracket));
+                let bracket = self.add_token(bracket.clone());
+                let index = self.lower_expr(index);
+                covering.add(self.expr_span(index));
+                covering.add(Span::from_token(operator));
+                let operator = self.add_token(operator.clone());
+                let value = self.lower_expr(value);
+                covering.add(self.expr_span(value));
+                ArenaExpr::IndexSet {
+                    object,
+                    bracket,
+                    index,
+                    operator,
+                    value,
+                }
+            }
+            Expr::List { elements, span } => {
+                let mut ids = Vec::with_capacity(elements.len());
+                for node in elements {
+                    let id = self.lower_expr(node);
+                    covering.add(self.expr_span(id));
+                    ids.push(id);
+                }
+                let elements = self.add_expr_list(&ids);
+                covering.add(*span);
+                ArenaExpr::List { elements }
+            }
+            Expr::Literal { value, span } => {
+                let value = self.add_literal_type(value.clone());
+                covering.add(*span);
+                ArenaExpr::Literal { value }
+            }
+            Expr::Map { entries, span } => {
+                let mut ids = Vec::with_capacity(entries.len());
+                for node in entries {
+                    let id = self.lower_expr(node);
+                    covering.add(self.expr_span(id));
+                    ids.push(id);
+                }
+                let entries = self.add_expr_list(&ids);
+                covering.add(*span);
+                ArenaExpr::Map { entries }
+            }
+            Expr::Unary { operator, right } => {
+                covering.add(Span::from_token(operator));
+                let operator = self.add_token(operator.clone());
+                let right = self.lower_expr(right);
+                covering.add(self.expr_span(right));
+                ArenaExpr::Unary { operator, right }
+            }
+            Expr::Variable { name } => {
+                covering.add(Span::from_token(name));
+                let name = self.add_token(name.clone());
+                ArenaExpr::Variable { name }
+            }
+        };
+        self.alloc_expr(node, covering.span())
+    }
+
+    // Same output as the Display of Expr.
+    pub fn display_expr(&self, id: ExprId) -> DisplayExpr<'_> {
+        DisplayExpr { arena: self, id }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DisplayExpr<'a> {
+    arena: &'a Arena,
+    id: ExprId,
+}
+
+impl fmt::Display for DisplayExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arena = self.arena;
+        match arena.expr(self.id) {
+            ArenaExpr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = arena.display_expr(left);
+                let operator = arena.token(operator);
+                let right = arena.display_expr(right);
+                write!(f, "({operator} {left} {right})")
+            }
+            ArenaExpr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = arena.display_expr(condition);
+                let then_branch = arena.display_expr(then_branch);
+                let else_branch = arena.display_expr(else_branch);
+                write!(f, "(?: {condition} {then_branch} {else_branch})")
+            }
+            ArenaExpr::Grouping { expression } => {
+                let expression = arena.display_expr(expression);
+                write!(f, "(group {expression})")
+            }
+            ArenaExpr::Index { object, index, .. } => {
+                let object = arena.display_expr(object);
+                let index = arena.display_expr(index);
+                write!(f, "(index {object} {index})")
+            }
+            ArenaExpr::IndexSet {
+                object,
+                index,
+                operator,
+                value,
+                ..
+            } => {
+                let object = arena.display_expr(object);
+                let index = arena.display_expr(index);
+                let operator = arena.token(operator);
+                let value = arena.display_expr(value);
+                write!(f, "({operator} (index {object} {index}) {value})")
+            }
+            ArenaExpr::List { elements } => {
+                let elements = arena.expr_list(elements).iter();
+                let elements = Spaced(elements.map(|&id| arena.display_expr(id)));
+                write!(f, "(list{elements})")
+            }
+            ArenaExpr::Literal { value } => {
+                let value = arena.literal_type(value);
+                write!(f, "{value}")
+            }
+            ArenaExpr::Map { entries } => {
+                let entries = arena.expr_list(entries).iter();
+                let entries = Spaced(entries.map(|&id| arena.display_expr(id)));
+                write!(f, "(map{entries})")
+            }
+            ArenaExpr::Unary { operator, right } => {
+                let operator = arena.token(operator);
+                let right = arena.display_expr(right);
+                write!(f, "({operator} {right})")
+            }
+            ArenaExpr::Variable { name } => {
+                let name = arena.token(name);
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+// Items each preceded by a space, for lists of children in the formats above.
+struct Spaced<I>(I);
+
+impl<I> fmt::Display for Spaced<I>
+where
+    I: Iterator + Clone,
+    I::Item: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in self.0.clone() {
+            write!(f, " {item}")?;
+        }
+        Ok(())
+    }
+}
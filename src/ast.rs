@@ -1,62 +1,340 @@
+// Generated by generate_ast from src/grammar.ast, do not edit by hand.
+
 use core::fmt;
-// In the book, they generate this automatically.
-// I will do it manually until I understand what's going on.
+
 use crate::scanner::{LiteralType, Token};
+pub use crate::span::Span;
 
+/// An expression, produces a value when evaluated.
 pub enum Expr {
+    /// Infix operator applied to two operands, e.g. `1 + 2`.
+    /// The comma operator is a binary one too, `a, b` evaluates both and produces `b`.
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
-    Grouping {
-        expression: Box<Expr>,
+    /// `condition ? then_branch : else_branch`, evaluates only one of the branches.
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
     },
-    Literal(LiteralType),
-    Unary {
+    /// Expression in parentheses, e.g. `(1 + 2)`.
+    /// The span covers the parentheses, they are not stored anywhere else.
+    Grouping { expression: Box<Expr>, span: Span },
+    /// Element of a list, e.g. `xs[0]`.
+    /// The bracket is the closing one, runtime errors about the index point at it.
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// Assignment to an element of a list or a map, e.g. `xs[0] = 1`.
+    /// The operator is `=`, or one of `+=`, `-=`, `*=`, `/=` which combine the element with the value,
+    /// `xs[i()] += 1` evaluates `xs` and `i()` only once. The parser turns `++xs[0]` into `xs[0] += 1`,
+    /// and keeps the operator of `xs[0]++` which does the same but produces the old value.
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
         operator: Token,
-        right: Box<Expr>,
+        value: Box<Expr>,
     },
+    /// List literal, e.g. `[1, 2, 3]`. The span covers the brackets.
+    List { elements: Vec<Expr>, span: Span },
+    /// Number, string, boolean or `nil`.
+    Literal { value: LiteralType, span: Span },
+    /// Map literal, e.g. `{"a": 1, "b": 2}`. Keys and values alternate in `entries`,
+    /// in source order, which is the order they are evaluated in. The span covers the braces.
+    Map { entries: Vec<Expr>, span: Span },
+    /// Prefix operator applied to one operand, e.g. `-1` or `!true`.
+    Unary { operator: Token, right: Box<Expr> },
+    /// Name of a variable, e.g. `xs`.
+    Variable { name: Token },
 }
 
 impl fmt::Display for Expr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            Expr::Unary { operator, right } => write!(f, "({operator} {right})"),
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => {
-                write!(f, "({operator} {left} {right})")
+            } => write!(f, "({operator} {left} {right})"),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => write!(f, "(?: {condition} {then_branch} {else_branch})"),
+            Expr::Grouping { expression, .. } => write!(f, "(group {expression})"),
+            Expr::Index { object, index, .. } => write!(f, "(index {object} {index})"),
+            Expr::IndexSet {
+                object,
+                index,
+                operator,
+                value,
+                ..
+            } => write!(f, "({operator} (index {object} {index}) {value})"),
+            Expr::List { elements, .. } => {
+                write!(f, "(list{elements})", elements = Spaced(elements))
             }
-            // TODO: the book prints nil if literal is null, how will we do it?
-            Expr::Literal(value) => write!(f, "{value}"),
-            Expr::Grouping { expression } => write!(f, "(group {expression})"),
+            Expr::Literal { value, .. } => write!(f, "{value}"),
+            Expr::Map { entries, .. } => write!(f, "(map{entries})", entries = Spaced(entries)),
+            Expr::Unary { operator, right } => write!(f, "({operator} {right})"),
+            Expr::Variable { name } => write!(f, "{name}"),
         }
     }
 }
 
-#[cfg(test)]
-mod dests {
-    use super::*;
-    use crate::scanner::{LiteralType, Numeric};
-    use crate::types::TokenType;
-
-    #[test]
-    fn test_expr_display() {
-        let expression = Expr::Binary {
-            left: Box::new(Expr::Unary {
-                operator: Token::new(TokenType::Minus, "-".to_string(), LiteralType::Null, 1),
-                right: Box::new(Expr::Literal(LiteralType::Num(Numeric::Integer(123)))),
-            }),
-            operator: Token::new(TokenType::Star, "*".to_string(), LiteralType::Null, 1),
-            right: Box::new(Expr::Grouping {
-                expression: Box::new(Expr::Literal(LiteralType::Num(Numeric::Float(45.67)))),
-            }),
-        };
-        let res = format!("{expression}");
-        println!("{res}");
-        assert!(format!("{expression}") == "(* (- 123) (group 45.67))");
+// Traversal of the tree for passes that only read it. Every visit method walks
+// the children by default, so a pass overrides only the nodes it cares about.
+// Override visit_expr to do something for every node, and call walk_expr
+// from it to keep going down.
+pub trait ExprVisitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+    fn visit_binary(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right);
+    }
+    fn visit_conditional(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        self.visit_expr(condition);
+        self.visit_expr(then_branch);
+        self.visit_expr(else_branch);
+    }
+    fn visit_grouping(&mut self, expression: &Expr, _span: &Span) {
+        self.visit_expr(expression);
+    }
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+    }
+    fn visit_index_set(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        _operator: &Token,
+        value: &Expr,
+    ) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+        self.visit_expr(value);
+    }
+    fn visit_list(&mut self, elements: &[Expr], _span: &Span) {
+        for expr in elements {
+            self.visit_expr(expr);
+        }
+    }
+    fn visit_literal(&mut self, _value: &LiteralType, _span: &Span) {}
+    fn visit_map(&mut self, entries: &[Expr], _span: &Span) {
+        for expr in entries {
+            self.visit_expr(expr);
+        }
+    }
+    fn visit_unary(&mut self, _operator: &Token, right: &Expr) {
+        self.visit_expr(right);
+    }
+    fn visit_variable(&mut self, _name: &Token) {}
+}
+
+// Dispatches to the visit method of the node kind.
+pub fn walk_expr<V: ExprVisitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => visitor.visit_binary(left, operator, right),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => visitor.visit_conditional(condition, then_branch, else_branch),
+        Expr::Grouping { expression, span } => visitor.visit_grouping(expression, span),
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => visitor.visit_index(object, bracket, index),
+        Expr::IndexSet {
+            object,
+            bracket,
+            index,
+            operator,
+            value,
+        } => visitor.visit_index_set(object, bracket, index, operator, value),
+        Expr::List { elements, span } => visitor.visit_list(elements, span),
+        Expr::Literal { value, span } => visitor.visit_literal(value, span),
+        Expr::Map { entries, span } => visitor.visit_map(entries, span),
+        Expr::Unary { operator, right } => visitor.visit_unary(operator, right),
+        Expr::Variable { name } => visitor.visit_variable(name),
     }
 }
+
+// Same as ExprVisitor, but for passes that rewrite the tree in place.
+pub trait ExprVisitorMut {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+    fn visit_binary(&mut self, left: &mut Expr, _operator: &mut Token, right: &mut Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right);
+    }
+    fn visit_conditional(
+        &mut self,
+        condition: &mut Expr,
+        then_branch: &mut Expr,
+        else_branch: &mut Expr,
+    ) {
+        self.visit_expr(condition);
+        self.visit_expr(then_branch);
+        self.visit_expr(else_branch);
+    }
+    fn visit_grouping(&mut self, expression: &mut Expr, _span: &mut Span) {
+        self.visit_expr(expression);
+    }
+    fn visit_index(&mut self, object: &mut Expr, _bracket: &mut Token, index: &mut Expr) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+    }
+    fn visit_index_set(
+        &mut self,
+        object: &mut Expr,
+        _bracket: &mut Token,
+        index: &mut Expr,
+        _operator: &mut Token,
+        value: &mut Expr,
+    ) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+        self.visit_expr(value);
+    }
+    fn visit_list(&mut self, elements: &mut Vec<Expr>, _span: &mut Span) {
+        for expr in elements {
+            self.visit_expr(expr);
+        }
+    }
+    fn visit_literal(&mut self, _value: &mut LiteralType, _span: &mut Span) {}
+    fn visit_map(&mut self, entries: &mut Vec<Expr>, _span: &mut Span) {
+        for expr in entries {
+            self.visit_expr(expr);
+        }
+    }
+    fn visit_unary(&mut self, _operator: &mut Token, right: &mut Expr) {
+        self.visit_expr(right);
+    }
+    fn visit_variable(&mut self, _name: &mut Token) {}
+}
+
+pub fn walk_expr_mut<V: ExprVisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => visitor.visit_binary(left, operator, right),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => visitor.visit_conditional(condition, then_branch, else_branch),
+        Expr::Grouping { expression, span } => visitor.visit_grouping(expression, span),
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => visitor.visit_index(object, bracket, index),
+        Expr::IndexSet {
+            object,
+            bracket,
+            index,
+            operator,
+            value,
+        } => visitor.visit_index_set(object, bracket, index, operator, value),
+        Expr::List { elements, span } => visitor.visit_list(elements, span),
+        Expr::Literal { value, span } => visitor.visit_literal(value, span),
+        Expr::Map { entries, span } => visitor.visit_map(entries, span),
+        Expr::Unary { operator, right } => visitor.visit_unary(operator, right),
+        Expr::Variable { name } => visitor.visit_variable(name),
+    }
+}
+
+// Traversal which produces a value for every node, e.g. an evaluator. There is no
+// default walk, a method gets the children of its node and calls fold_expr on those
+// it needs. The result may borrow from the tree, which lives for 'a.
+pub trait ExprFold<'a, R> {
+    fn fold_binary(&mut self, left: &'a Expr, operator: &'a Token, right: &'a Expr) -> R;
+    fn fold_conditional(
+        &mut self,
+        condition: &'a Expr,
+        then_branch: &'a Expr,
+        else_branch: &'a Expr,
+    ) -> R;
+    fn fold_grouping(&mut self, expression: &'a Expr, span: &'a Span) -> R;
+    fn fold_index(&mut self, object: &'a Expr, bracket: &'a Token, index: &'a Expr) -> R;
+    fn fold_index_set(
+        &mut self,
+        object: &'a Expr,
+        bracket: &'a Token,
+        index: &'a Expr,
+        operator: &'a Token,
+        value: &'a Expr,
+    ) -> R;
+    fn fold_list(&mut self, elements: &'a [Expr], span: &'a Span) -> R;
+    fn fold_literal(&mut self, value: &'a LiteralType, span: &'a Span) -> R;
+    fn fold_map(&mut self, entries: &'a [Expr], span: &'a Span) -> R;
+    fn fold_unary(&mut self, operator: &'a Token, right: &'a Expr) -> R;
+    fn fold_variable(&mut self, name: &'a Token) -> R;
+}
+
+// Dispatches to the fold method of the node kind.
+pub fn fold_expr<'a, R, V: ExprFold<'a, R> + ?Sized>(visitor: &mut V, expr: &'a Expr) -> R {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => visitor.fold_binary(left, operator, right),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => visitor.fold_conditional(condition, then_branch, else_branch),
+        Expr::Grouping { expression, span } => visitor.fold_grouping(expression, span),
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => visitor.fold_index(object, bracket, index),
+        Expr::IndexSet {
+            object,
+            bracket,
+            index,
+            operator,
+            value,
+        } => visitor.fold_index_set(object, bracket, index, operator, value),
+        Expr::List { elements, span } => visitor.fold_list(elements, span),
+        Expr::Literal { value, span } => visitor.fold_literal(value, span),
+        Expr::Map { entries, span } => visitor.fold_map(entries, span),
+        Expr::Unary { operator, right } => visitor.fold_unary(operator, right),
+        Expr::Variable { name } => visitor.fold_variable(name),
+    }
+}
+
+// Nodes each preceded by a space, for lists of children in the formats above.
+struct Spaced<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for Spaced<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in self.0 {
+            write!(f, " {item}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,153 @@
+use super::*;
+use crate::scanner::{LiteralType, Numeric};
+use crate::types::TokenType;
+
+fn span(col: usize, end_col: usize) -> Span {
+    Span {
+        line: 1,
+        col,
+        end_line: 1,
+        end_col,
+    }
+}
+
+// -123 * (45.67)
+fn example() -> Expr {
+    Expr::Binary {
+        left: Box::new(Expr::Unary {
+            operator: Token::new(TokenType::Minus, "-".to_string(), LiteralType::Null, 1, 1),
+            right: Box::new(Expr::Literal {
+                value: LiteralType::Num(Numeric::Integer(123)),
+                span: span(2, 5),
+            }),
+        }),
+        operator: Token::new(TokenType::Star, "*".to_string(), LiteralType::Null, 1, 6),
+        right: Box::new(Expr::Grouping {
+            expression: Box::new(Expr::Literal {
+                value: LiteralType::Num(Numeric::Float(45.67)),
+                span: span(9, 14),
+            }),
+            span: span(8, 15),
+        }),
+    }
+}
+
+#[test]
+fn test_expr_display() {
+    let expression = example();
+    let res = format!("{expression}");
+    assert_eq!(res, "(* (- 123) (group 45.67))");
+}
+
+#[test]
+fn test_expr_span() {
+    assert_eq!(example().span(), span(1, 15));
+}
+
+struct LiteralCounter(usize);
+
+impl ExprVisitor for LiteralCounter {
+    fn visit_literal(&mut self, _value: &LiteralType, _span: &Span) {
+        self.0 += 1;
+    }
+}
+
+#[test]
+fn test_visitor_walks_children() {
+    let mut counter = LiteralCounter(0);
+    counter.visit_expr(&example());
+    assert_eq!(counter.0, 2);
+}
+
+// Replaces every number with its negation.
+struct Negate;
+
+impl ExprVisitorMut for Negate {
+    fn visit_literal(&mut self, value: &mut LiteralType, _span: &mut Span) {
+        if let LiteralType::Num(Numeric::Float(n)) = value {
+            *n = -*n;
+        }
+        if let LiteralType::Num(Numeric::Integer(n)) = value {
+            *n = -*n;
+        }
+    }
+}
+
+#[test]
+fn test_mut_visitor_rewrites_tree() {
+    let mut expression = example();
+    Negate.visit_expr(&mut expression);
+    assert_eq!(format!("{expression}"), "(* (- -123) (group -45.67))");
+}
+
+// Value of an expression made of numbers and arithmetic.
+struct Eval;
+
+impl ExprFold<'_, f64> for Eval {
+    fn fold_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> f64 {
+        let (left, right) = (fold_expr(self, left), fold_expr(self, right));
+        match operator.ttype {
+            TokenType::Plus => left + right,
+            TokenType::Minus => left - right,
+            TokenType::Star => left * right,
+            TokenType::Slash => left / right,
+            _ => unreachable!("{operator}"),
+        }
+    }
+    fn fold_conditional(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> f64 {
+        if fold_expr(self, condition) != 0.0 {
+            fold_expr(self, then_branch)
+        } else {
+            fold_expr(self, else_branch)
+        }
+    }
+    fn fold_grouping(&mut self, expression: &Expr, _span: &Span) -> f64 {
+        fold_expr(self, expression)
+    }
+    fn fold_index(&mut self, _object: &Expr, _bracket: &Token, _index: &Expr) -> f64 {
+        unreachable!()
+    }
+    fn fold_index_set(
+        &mut self,
+        _object: &Expr,
+        _bracket: &Token,
+        _index: &Expr,
+        _operator: &Token,
+        _value: &Expr,
+    ) -> f64 {
+        unreachable!()
+    }
+    fn fold_list(&mut self, elements: &[Expr], _span: &Span) -> f64 {
+        elements
+            .iter()
+            .map(|element| fold_expr(self, element))
+            .sum()
+    }
+    fn fold_literal(&mut self, value: &LiteralType, _span: &Span) -> f64 {
+        match value {
+            LiteralType::Num(Numeric::Integer(n)) => *n as f64,
+            LiteralType::Num(Numeric::Float(n)) => *n,
+            _ => unreachable!("{value}"),
+        }
+    }
+    fn fold_map(&mut self, _entries: &[Expr], _span: &Span) -> f64 {
+        unreachable!()
+    }
+    fn fold_unary(&mut self, operator: &Token, right: &Expr) -> f64 {
+        assert_eq!(operator.ttype, TokenType::Minus);
+        -fold_expr(self, right)
+    }
+    fn fold_variable(&mut self, name: &Token) -> f64 {
+        unreachable!("{name}")
+    }
+}
+
+#[test]
+fn test_fold_returns_values() {
+    assert_eq!(fold_expr(&mut Eval, &example()), -123.0 * 45.67);
+}
@@ -0,0 +1,156 @@
+// Functions being called, for the stack traces of runtime errors.
+//
+// The interpreter pushes a frame when it calls a function and pops it when the call returns.
+// A frame only keeps the name of the function and the line it was called from, which is
+// where its caller is while it runs. The trace of an error lists the innermost frame first:
+//
+// [line 2] in inner()
+// [line 6] in outer()
+// [line 9] in script
+//
+// The depth is limited, a runaway recursion is a runtime error instead of overflowing
+// the stack of the interpreter and killing the process.
+
+use std::fmt::{self, Write};
+
+// Frames printed in a trace, deeper ones are counted instead. Enough to see the pattern
+// of a runaway recursion without filling the terminal.
+pub const TRACE_LIMIT: usize = 20;
+
+// Calls deep, when it isn't configured. Every call of a tree-walking interpreter takes
+// a few nested Rust calls, this leaves room for them on a main thread's 8 MiB stack.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+struct Frame {
+    function: String,
+    call_line: usize,
+}
+
+pub struct CallStack {
+    frames: Vec<Frame>,
+    max_depth: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StackOverflow;
+
+impl fmt::Display for StackOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stack overflow.")
+    }
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        Self::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            frames: Vec::new(),
+            max_depth,
+        }
+    }
+    // The frame isn't pushed when there is no room left for it.
+    pub fn push(&mut self, function: &str, call_line: usize) -> Result<(), StackOverflow> {
+        if self.frames.len() >= self.max_depth {
+            return Err(StackOverflow);
+        }
+        self.frames.push(Frame {
+            function: function.to_string(),
+            call_line,
+        });
+        Ok(())
+    }
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+    // Number of functions being called, the script itself isn't one.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Trace of an error on `line`, the innermost frame first and at most `limit` frames.
+    pub fn trace(&self, line: usize, limit: usize) -> String {
+        // Each frame is on the line its callee was called from, the innermost one on the error.
+        let lines = std::iter::once(line).chain(self.frames.iter().rev().map(|f| f.call_line));
+        let functions = self
+            .frames
+            .iter()
+            .rev()
+            .map(|f| format!("{}()", f.function))
+            .chain(std::iter::once("script".to_string()));
+        let mut out = String::new();
+        for (line, function) in lines.zip(functions).take(limit) {
+            writeln!(out, "[line {line}] in {function}").unwrap();
+        }
+        // The script is a frame of the trace too.
+        let total = self.frames.len() + 1;
+        if total > limit {
+            writeln!(out, "... {} frames omitted", total - limit).unwrap();
+        }
+        out
+    }
+}
+
+impl Default for CallStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let mut stack = CallStack::new();
+        assert_eq!(stack.trace(4, TRACE_LIMIT), "[line 4] in script\n");
+        stack.push("outer", 9).unwrap();
+        stack.push("inner", 6).unwrap();
+        assert_eq!(stack.depth(), 2);
+        let expected = "\
+[line 2] in inner()
+[line 6] in outer()
+[line 9] in script
+";
+        assert_eq!(stack.trace(2, TRACE_LIMIT), expected);
+        stack.pop();
+        assert_eq!(
+            stack.trace(7, TRACE_LIMIT),
+            "[line 7] in outer()\n[line 9] in script\n"
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion_trace() {
+        let mut stack = CallStack::new();
+        stack.push("count", 10).unwrap();
+        for _ in 0..999 {
+            stack.push("count", 3).unwrap();
+        }
+        let trace = stack.trace(3, 4);
+        let expected = "\
+[line 3] in count()
+[line 3] in count()
+[line 3] in count()
+[line 3] in count()
+... 997 frames omitted
+";
+        assert_eq!(trace, expected);
+        // Nothing is omitted when everything fits.
+        assert!(!stack.trace(3, 1001).contains("omitted"));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut stack = CallStack::with_max_depth(2);
+        assert_eq!(stack.push("f", 1), Ok(()));
+        assert_eq!(stack.push("f", 1), Ok(()));
+        assert_eq!(stack.push("f", 1), Err(StackOverflow));
+        assert_eq!(stack.depth(), 2);
+        stack.pop();
+        assert_eq!(stack.push("g", 1), Ok(()));
+        assert_eq!(StackOverflow.to_string(), "Stack overflow.");
+    }
+}
@@ -0,0 +1,552 @@
+// Debug output for the different stages of the interpreter.
+// Mostly useful when something goes wrong in the scanner or the parser.
+
+use std::fmt::Write;
+
+use crate::ast::{fold_expr, walk_expr, Expr, ExprFold, ExprVisitor, Span};
+use crate::scanner::{LiteralType, Numeric, Token};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TokenFormat {
+    Text,
+    Json,
+}
+
+impl TokenFormat {
+    // Parses the value of `tokens --format=`, `None` stands for the default.
+    pub fn from_flag(value: Option<&str>) -> Option<Self> {
+        match value {
+            None | Some("text") => Some(TokenFormat::Text),
+            Some("json") => Some(TokenFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AstFormat {
+    Sexpr,
+    Tree,
+    Json,
+    Dot,
+}
+
+impl AstFormat {
+    // Parses the value of `ast --format=`, `None` stands for the default.
+    pub fn from_flag(value: Option<&str>) -> Option<Self> {
+        match value {
+            None | Some("sexpr") => Some(AstFormat::Sexpr),
+            Some("tree") => Some(AstFormat::Tree),
+            Some("json") => Some(AstFormat::Json),
+            Some("dot") => Some(AstFormat::Dot),
+            _ => None,
+        }
+    }
+}
+
+pub fn dump_tokens(tokens: &[Token], format: TokenFormat) -> String {
+    match format {
+        TokenFormat::Text => tokens_text(tokens),
+        TokenFormat::Json => tokens_json(tokens),
+    }
+}
+
+// One token per line: `line:col  TYPE  'lexeme'  literal`.
+// Newlines and other control characters in strings are escaped to keep it that way.
+fn tokens_text(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let position = format!("{}:{}", token.line, token.col);
+        let ttype = format!("{:?}", token.ttype);
+        let mut line = format!("{position:<7}  {ttype:<12}  '");
+        escape_into(&mut line, &token.lexeme, false);
+        line.push_str("'  ");
+        escape_into(&mut line, &token.literal.to_string(), false);
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    out
+}
+
+fn tokens_json(tokens: &[Token]) -> String {
+    let mut out = String::from("[\n");
+    for (i, token) in tokens.iter().enumerate() {
+        let separator = if i + 1 == tokens.len() { "" } else { "," };
+        writeln!(
+            out,
+            "  {{\"line\": {}, \"col\": {}, \"type\": \"{:?}\", \"lexeme\": {}, \"literal\": {}}}{separator}",
+            token.line,
+            token.col,
+            token.ttype,
+            json_string(&token.lexeme),
+            json_literal(&token.literal)
+        )
+        .unwrap();
+    }
+    out.push_str("]\n");
+    out
+}
+
+pub fn dump_ast(expr: &Expr, format: AstFormat) -> String {
+    match format {
+        AstFormat::Sexpr => format!("{expr}\n"),
+        AstFormat::Tree => {
+            let mut printer = TreePrinter::default();
+            printer.visit_expr(expr);
+            printer.out
+        }
+        AstFormat::Json => {
+            let mut printer = JsonPrinter::default();
+            printer.visit_expr(expr);
+            printer.out + "\n"
+        }
+        AstFormat::Dot => {
+            let mut printer = DotPrinter {
+                out: String::from("digraph ast {\n    node [shape=box];\n"),
+                ..Default::default()
+            };
+            printer.node(expr);
+            printer.out + "}\n"
+        }
+    }
+}
+
+fn ast_kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Binary { .. } => "Binary",
+        Expr::Conditional { .. } => "Conditional",
+        Expr::Grouping { .. } => "Grouping",
+        Expr::Index { .. } => "Index",
+        Expr::IndexSet { .. } => "IndexSet",
+        Expr::List { .. } => "List",
+        Expr::Literal { .. } => "Literal",
+        Expr::Map { .. } => "Map",
+        Expr::Unary { .. } => "Unary",
+        Expr::Variable { .. } => "Variable",
+    }
+}
+
+// Node kind and its own data, without the children.
+fn ast_label(expr: &Expr) -> String {
+    let kind = ast_kind(expr);
+    match expr {
+        Expr::Binary { operator, .. }
+        | Expr::IndexSet { operator, .. }
+        | Expr::Unary { operator, .. } => format!("{kind} {operator}"),
+        Expr::Literal { value, .. } => format!("{kind} {}", literal_repr(value)),
+        Expr::Variable { name } => format!("{kind} {name}"),
+        Expr::Conditional { .. }
+        | Expr::Grouping { .. }
+        | Expr::Index { .. }
+        | Expr::List { .. }
+        | Expr::Map { .. } => kind.to_string(),
+    }
+}
+
+// Like the Display of LiteralType, but tells strings apart from other values.
+fn literal_repr(value: &LiteralType) -> String {
+    match value {
+        LiteralType::Str(s) => format!("{s:?}"),
+        LiteralType::Null => "nil".to_string(),
+        value => value.to_string(),
+    }
+}
+
+// Every node on its own line, children are indented by two spaces:
+//
+// Binary *
+//   Literal 1
+//   Literal 2
+#[derive(Default)]
+struct TreePrinter {
+    out: String,
+    depth: usize,
+}
+
+impl ExprVisitor for TreePrinter {
+    fn visit_expr(&mut self, expr: &Expr) {
+        writeln!(self.out, "{}{}", "  ".repeat(self.depth), ast_label(expr)).unwrap();
+        self.depth += 1;
+        walk_expr(self, expr);
+        self.depth -= 1;
+    }
+}
+
+// Every node is an object with the following keys:
+//
+// "type": node kind, one of "Binary", "Conditional", "Grouping", "Index", "IndexSet",
+// "List", "Literal", "Map", "Unary", "Variable".
+// "span": {"line", "col", "end_line", "end_col"}, 1-based, the end is exclusive.
+// "operator": lexeme of the operator, Binary, IndexSet and Unary only.
+// "value": string, number, bool or null, Literal only.
+// "name": name of the variable, Variable only.
+// "left", "right", "condition", "then_branch", "else_branch", "expression",
+// "object", "index", "value" (IndexSet only): child nodes, named as the fields of ast::Expr.
+// "elements": array of child nodes, List only.
+// "entries": array of child nodes, keys and values alternating, Map only.
+#[derive(Default)]
+struct JsonPrinter {
+    out: String,
+    depth: usize,
+}
+
+impl JsonPrinter {
+    fn field(&mut self, name: &str, value: &str) {
+        let indent = "  ".repeat(self.depth);
+        write!(self.out, ",\n{indent}\"{name}\": {value}").unwrap();
+    }
+    fn child(&mut self, name: &str, expr: &Expr) {
+        self.field(name, "");
+        self.visit_expr(expr);
+    }
+    fn children(&mut self, name: &str, exprs: &[Expr]) {
+        if exprs.is_empty() {
+            self.field(name, "[]");
+            return;
+        }
+        self.field(name, "[");
+        self.depth += 1;
+        for (i, expr) in exprs.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(self.out, "{separator}\n{}", "  ".repeat(self.depth)).unwrap();
+            self.visit_expr(expr);
+        }
+        self.depth -= 1;
+        write!(self.out, "\n{}]", "  ".repeat(self.depth)).unwrap();
+    }
+}
+
+impl ExprVisitor for JsonPrinter {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.depth += 1;
+        let indent = "  ".repeat(self.depth);
+        writeln!(self.out, "{{").unwrap();
+        write!(self.out, "{indent}\"type\": \"{}\"", ast_kind(expr)).unwrap();
+        self.field("span", &json_span(expr.span()));
+        walk_expr(self, expr);
+        self.depth -= 1;
+        write!(self.out, "\n{}}}", "  ".repeat(self.depth)).unwrap();
+    }
+    fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+        self.field("operator", &json_string(&operator.lexeme));
+        self.child("left", left);
+        self.child("right", right);
+    }
+    fn visit_conditional(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        self.child("condition", condition);
+        self.child("then_branch", then_branch);
+        self.child("else_branch", else_branch);
+    }
+    fn visit_grouping(&mut self, expression: &Expr, _span: &Span) {
+        self.child("expression", expression);
+    }
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        self.child("object", object);
+        self.child("index", index);
+    }
+    fn visit_index_set(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        operator: &Token,
+        value: &Expr,
+    ) {
+        self.field("operator", &json_string(&operator.lexeme));
+        self.child("object", object);
+        self.child("index", index);
+        self.child("value", value);
+    }
+    fn visit_list(&mut self, elements: &[Expr], _span: &Span) {
+        self.children("elements", elements);
+    }
+    fn visit_map(&mut self, entries: &[Expr], _span: &Span) {
+        self.children("entries", entries);
+    }
+    fn visit_literal(&mut self, value: &LiteralType, _span: &Span) {
+        self.field("value", &json_literal(value));
+    }
+    fn visit_unary(&mut self, operator: &Token, right: &Expr) {
+        self.field("operator", &json_string(&operator.lexeme));
+        self.child("right", right);
+    }
+    fn visit_variable(&mut self, name: &Token) {
+        self.field("name", &json_string(&name.lexeme));
+    }
+}
+
+fn json_span(span: Span) -> String {
+    format!(
+        "{{\"line\": {}, \"col\": {}, \"end_line\": {}, \"end_col\": {}}}",
+        span.line, span.col, span.end_line, span.end_col
+    )
+}
+
+// Graphviz graph, nodes are numbered in pre-order.
+#[derive(Default)]
+struct DotPrinter {
+    out: String,
+    next_id: usize,
+}
+
+impl DotPrinter {
+    // Prints the node and its subtree, returns the id of the node.
+    fn node(&mut self, expr: &Expr) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        // DOT strings are escaped the same way as JSON ones.
+        writeln!(
+            self.out,
+            "    n{id} [label={}];",
+            json_string(&ast_label(expr))
+        )
+        .unwrap();
+        for (name, child) in fold_expr(&mut NamedChildren, expr) {
+            let child_id = self.node(child);
+            writeln!(self.out, "    n{id} -> n{child_id} [label=\"{name}\"];").unwrap();
+        }
+        id
+    }
+}
+
+// Children of a node with the names of their edges in the graph.
+struct NamedChildren;
+
+type Edges<'a> = Vec<(String, &'a Expr)>;
+
+impl<'a> ExprFold<'a, Edges<'a>> for NamedChildren {
+    fn fold_binary(&mut self, left: &'a Expr, _operator: &Token, right: &'a Expr) -> Edges<'a> {
+        vec![("left".to_string(), left), ("right".to_string(), right)]
+    }
+    fn fold_conditional(
+        &mut self,
+        condition: &'a Expr,
+        then_branch: &'a Expr,
+        else_branch: &'a Expr,
+    ) -> Edges<'a> {
+        vec![
+            ("condition".to_string(), condition),
+            ("then_branch".to_string(), then_branch),
+            ("else_branch".to_string(), else_branch),
+        ]
+    }
+    fn fold_grouping(&mut self, expression: &'a Expr, _span: &Span) -> Edges<'a> {
+        vec![("expression".to_string(), expression)]
+    }
+    fn fold_index(&mut self, object: &'a Expr, _bracket: &Token, index: &'a Expr) -> Edges<'a> {
+        vec![("object".to_string(), object), ("index".to_string(), index)]
+    }
+    fn fold_index_set(
+        &mut self,
+        object: &'a Expr,
+        _bracket: &Token,
+        index: &'a Expr,
+        _operator: &Token,
+        value: &'a Expr,
+    ) -> Edges<'a> {
+        vec![
+            ("object".to_string(), object),
+            ("index".to_string(), index),
+            ("value".to_string(), value),
+        ]
+    }
+    fn fold_list(&mut self, elements: &'a [Expr], _span: &Span) -> Edges<'a> {
+        let names = (0..).map(|i| format!("elements[{i}]"));
+        names.zip(elements).collect()
+    }
+    fn fold_literal(&mut self, _value: &LiteralType, _span: &Span) -> Edges<'a> {
+        Vec::new()
+    }
+    fn fold_map(&mut self, entries: &'a [Expr], _span: &Span) -> Edges<'a> {
+        let names = (0..).flat_map(|i| [format!("keys[{i}]"), format!("values[{i}]")]);
+        names.zip(entries).collect()
+    }
+    fn fold_unary(&mut self, _operator: &Token, right: &'a Expr) -> Edges<'a> {
+        vec![("right".to_string(), right)]
+    }
+    fn fold_variable(&mut self, _name: &Token) -> Edges<'a> {
+        Vec::new()
+    }
+}
+
+pub fn json_literal(literal: &LiteralType) -> String {
+    match literal {
+        LiteralType::Str(s) => json_string(s),
+        LiteralType::Num(Numeric::Integer(n)) => n.to_string(),
+        // JSON has no NaN or infinity, the scanner can't produce them anyway.
+        LiteralType::Num(Numeric::Float(n)) if n.is_finite() => n.to_string(),
+        LiteralType::Num(Numeric::Float(_)) => "null".to_string(),
+        LiteralType::Bool(b) => b.to_string(),
+        LiteralType::Null => "null".to_string(),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    escape_into(&mut out, s, true);
+    out.push('"');
+    out
+}
+
+// Backslash escapes for backslashes and control characters, and double quotes if asked.
+fn escape_into(out: &mut String, s: &str, quotes: bool) {
+    for c in s.chars() {
+        match c {
+            '"' if quotes => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{ErrorHandler, Scanner};
+
+    fn scan(source: &str) -> Vec<Token> {
+        let mut err_handler = ErrorHandler::new();
+        let mut scanner = Scanner::new(source, &mut err_handler);
+        scanner.scan_tokens();
+        scanner.tokens
+    }
+
+    fn parse(source: &str) -> Expr {
+        crate::parse(source, &mut ErrorHandler::new()).unwrap()
+    }
+
+    #[test]
+    fn test_tokens_text() {
+        let tokens = scan("(1 +\n  \"hi\")");
+        let expected = "\
+1:1      LeftParen     '('
+1:2      Number        '1'  1
+1:4      Plus          '+'
+2:3      String        '\"hi\"'  hi
+2:7      RightParen    ')'
+2:8      Eof           ''
+";
+        assert_eq!(dump_tokens(&tokens, TokenFormat::Text), expected);
+    }
+
+    #[test]
+    fn test_tokens_text_escapes_strings() {
+        let tokens = scan("\"a\n\tb\\\" 1");
+        let expected = "\
+1:1      String        '\"a\\n\\tb\\\\\"'  a\\n\\tb\\\\
+2:6      Number        '1'  1
+2:7      Eof           ''
+";
+        assert_eq!(dump_tokens(&tokens, TokenFormat::Text), expected);
+    }
+
+    #[test]
+    fn test_tokens_json() {
+        let tokens = scan("\"a\\b\"");
+        let expected = "[
+  {\"line\": 1, \"col\": 1, \"type\": \"String\", \"lexeme\": \"\\\"a\\\\b\\\"\", \"literal\": \"a\\\\b\"},
+  {\"line\": 1, \"col\": 6, \"type\": \"Eof\", \"lexeme\": \"\", \"literal\": null}
+]
+";
+        assert_eq!(dump_tokens(&tokens, TokenFormat::Json), expected);
+    }
+
+    #[test]
+    fn test_ast_tree() {
+        let expected = "\
+Binary *
+  Unary -
+    Literal 1
+  Grouping
+    Literal \"a\"
+";
+        assert_eq!(dump_ast(&parse("-1 * (\"a\")"), AstFormat::Tree), expected);
+    }
+
+    #[test]
+    fn test_ast_json() {
+        let expected = r#"{
+  "type": "Unary",
+  "span": {"line": 1, "col": 1, "end_line": 1, "end_col": 5},
+  "operator": "-",
+  "right": {
+    "type": "Grouping",
+    "span": {"line": 1, "col": 2, "end_line": 1, "end_col": 5},
+    "expression": {
+      "type": "Literal",
+      "span": {"line": 1, "col": 3, "end_line": 1, "end_col": 4},
+      "value": 2
+    }
+  }
+}
+"#;
+        assert_eq!(dump_ast(&parse("-(2)"), AstFormat::Json), expected);
+    }
+
+    #[test]
+    fn test_ast_json_list() {
+        let expected = r#"{
+  "type": "Index",
+  "span": {"line": 1, "col": 1, "end_line": 1, "end_col": 11},
+  "object": {
+    "type": "List",
+    "span": {"line": 1, "col": 1, "end_line": 1, "end_col": 8},
+    "elements": [
+      {
+        "type": "Variable",
+        "span": {"line": 1, "col": 2, "end_line": 1, "end_col": 3},
+        "name": "a"
+      },
+      {
+        "type": "List",
+        "span": {"line": 1, "col": 5, "end_line": 1, "end_col": 7},
+        "elements": []
+      }
+    ]
+  },
+  "index": {
+    "type": "Literal",
+    "span": {"line": 1, "col": 9, "end_line": 1, "end_col": 10},
+    "value": 0
+  }
+}
+"#;
+        assert_eq!(dump_ast(&parse("[a, []][0]"), AstFormat::Json), expected);
+    }
+
+    #[test]
+    fn test_ast_dot() {
+        let expected = "\
+digraph ast {
+    node [shape=box];
+    n0 [label=\"Binary +\"];
+    n1 [label=\"Literal 1\"];
+    n0 -> n1 [label=\"left\"];
+    n2 [label=\"Literal 2\"];
+    n0 -> n2 [label=\"right\"];
+}
+";
+        assert_eq!(dump_ast(&parse("1 + 2"), AstFormat::Dot), expected);
+    }
+
+    #[test]
+    fn test_ast_dot_edge_names() {
+        let expected = "\
+digraph ast {
+    node [shape=box];
+    n0 [label=\"Map\"];
+    n1 [label=\"Literal \\\"a\\\"\"];
+    n0 -> n1 [label=\"keys[0]\"];
+    n2 [label=\"List\"];
+    n3 [label=\"Variable x\"];
+    n2 -> n3 [label=\"elements[0]\"];
+    n0 -> n2 [label=\"values[0]\"];
+}
+";
+        assert_eq!(dump_ast(&parse("{\"a\": [x]}"), AstFormat::Dot), expected);
+    }
+}
@@ -1,54 +1,1382 @@
-// This file is not used for now. Until I understand what's going on, I'll write everything
-// manually. The grammar is small for now.
+// Generates src/ast.rs from the grammar description in src/grammar.ast.
+// Run `just ast` after changing the grammar, a test makes sure they stay in sync.
+//
+// Also writes src/arena/nodes.rs, the same tree stored in an arena, and docs/ast.md,
+// a Markdown reference of the AST.
+//
+// The grammar file has the following lines:
+// - `# comment`, skipped, as are empty lines;
+// - `/// doc`, documentation of the base or variant on the next line;
+// - `use ...;`, copied to the top of the generated file as is;
+// - `Expr`, starts a new enum (a "base");
+// - `  Binary : Expr left, Token operator, Expr right => "({operator} {left} {right})"`,
+//   an indented variant of the last base with its fields and the Display format.
+//   Fields of a base type are boxed, as are those of an `Option<Base>`, `Vec<Base>` fields
+//   are kept as they are. All of them are visited as children by the generated visitors.
+//   A `Vec<Base>` field in the format prints each node preceded by a space,
+//   e.g. `"(list{elements})"` gives `(list 1 2)`, and `(list)` when empty.
+//   An `Option<Base>` field prints the same way, `"(return{value})"` gives `(return)`
+//   when there is no value.
 
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::fmt::{self, Write as FmtWrite};
+use std::fs;
 
-pub fn define_ast(output_fpath: String, base_name: String, types: Vec<String>) {
-    // The original in the book had an output directory.
-    // I think, using a file is better at this point.
-    // This might change in the future.
-    let path: String = output_fpath.to_string();
-    let file = File::create(path).unwrap();
-    let mut writer = BufWriter::new(&file);
-    writeln!(writer, "pub struct {base_name} {{").unwrap();
-    writeln!(writer, "}}").unwrap();
+// The output is formatted exactly as rustfmt would with its default settings, so that
+// `cargo fmt` leaves src/ast.rs alone and the up-to-date test keeps passing after it.
+// Line width we format to, same as rustfmt.
+const MAX_WIDTH: usize = 100;
+// Struct patterns with longer field lists are split over lines by rustfmt.
+const STRUCT_LIT_WIDTH: usize = 18;
+// Same for the fields of struct variants in enum definitions.
+const STRUCT_VARIANT_WIDTH: usize = 35;
 
-    for type_str in types {
-        let split_str: Vec<&str> = type_str.split(":").collect();
-        let struct_name = split_str[0].trim().to_string();
-        let fields = split_str[1].trim().to_string();
-        define_type(&mut writer, base_name.clone(), struct_name, fields);
+pub struct Grammar {
+    pub uses: Vec<String>,
+    pub bases: Vec<Base>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GrammarError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub struct Base {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub variants: Vec<Variant>,
+}
+
+pub struct Variant {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub fields: Vec<Field>,
+    pub display: String,
+}
+
+pub struct Field {
+    pub ty: String,
+    pub name: String,
+}
+
+enum FieldKind<'a> {
+    // Boxed node of the given base.
+    Child(&'a str),
+    // Vec of nodes of the given base.
+    Children(&'a str),
+    // Boxed node of the given base, or None.
+    OptionalChild(&'a str),
+    Data,
+}
+
+impl Grammar {
+    fn is_base(&self, name: &str) -> bool {
+        self.bases.iter().any(|b| b.name == name)
+    }
+    fn field_kind<'a>(&self, field: &'a Field) -> FieldKind<'a> {
+        if self.is_base(&field.ty) {
+            return FieldKind::Child(&field.ty);
+        }
+        let generic = |prefix: &str, suffix: &str| {
+            let inner = field.ty.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            self.is_base(inner).then_some(inner)
+        };
+        if let Some(inner) = generic("Vec<", ">") {
+            return FieldKind::Children(inner);
+        }
+        // `Option<Box<Expr>>` is accepted too, it is what the field ends up as.
+        match generic("Option<", ">").or_else(|| generic("Option<Box<", ">>")) {
+            Some(inner) => FieldKind::OptionalChild(inner),
+            None => FieldKind::Data,
+        }
+    }
+}
+
+pub fn parse_grammar(source: &str) -> Result<Grammar, GrammarError> {
+    let mut grammar = Grammar {
+        uses: Vec::new(),
+        bases: Vec::new(),
+    };
+    // Doc comment lines waiting for the base or variant they belong to.
+    let mut docs: Vec<String> = Vec::new();
+    let mut docs_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String| GrammarError {
+            line: line_number,
+            message,
+        };
+        let trimmed = line.trim();
+        if let Some(doc) = trimmed.strip_prefix("///") {
+            if docs.is_empty() {
+                docs_line = line_number;
+            }
+            docs.push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        let is_use = trimmed.starts_with("use ") || trimmed.starts_with("pub use ");
+        if !docs.is_empty() && (trimmed.is_empty() || is_use) {
+            return Err(error(
+                "Expect a base or a variant after the doc comment.".to_string(),
+            ));
+        }
+        if trimmed.is_empty() {
+            // Keep the use lines grouped the way they are in the grammar.
+            if grammar.bases.is_empty() && grammar.uses.last().is_some_and(|l| !l.is_empty()) {
+                grammar.uses.push(String::new());
+            }
+        } else if is_use {
+            if !grammar.bases.is_empty() {
+                return Err(error("Use lines should come before the bases.".to_string()));
+            }
+            grammar.uses.push(trimmed.to_string());
+        } else if line.starts_with(char::is_whitespace) {
+            let mut variant = parse_variant(trimmed).map_err(error)?;
+            variant.docs = std::mem::take(&mut docs);
+            let Some(base) = grammar.bases.last_mut() else {
+                return Err(error(format!(
+                    "Variant '{}' should come after its base, e.g. 'Expr'.",
+                    variant.name
+                )));
+            };
+            if base.variants.iter().any(|v| v.name == variant.name) {
+                return Err(error(format!(
+                    "Duplicate variant '{}' in '{}'.",
+                    variant.name, base.name
+                )));
+            }
+            base.variants.push(variant);
+        } else {
+            if !is_type_name(trimmed) {
+                return Err(error(format!(
+                    "Expect a base name like 'Expr', got '{trimmed}'."
+                )));
+            }
+            if grammar.is_base(trimmed) {
+                return Err(error(format!("Duplicate base '{trimmed}'.")));
+            }
+            grammar.bases.push(Base {
+                name: trimmed.to_string(),
+                docs: std::mem::take(&mut docs),
+                variants: Vec::new(),
+            });
+        }
+    }
+    if !docs.is_empty() {
+        return Err(GrammarError {
+            line: docs_line,
+            message: "Expect a base or a variant after the doc comment.".to_string(),
+        });
+    }
+    while grammar.uses.last().is_some_and(|l| l.is_empty()) {
+        grammar.uses.pop();
+    }
+    Ok(grammar)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_type_name(name: &str) -> bool {
+    is_identifier(name) && name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+// Splits on commas which are not inside of generic arguments, e.g. `HashMap<K, V>`.
+fn split_fields(fields: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in fields.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&fields[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
     }
+    result.push(&fields[start..]);
+    result
 }
 
-fn define_type<W: Write>(writer: &mut W, _base_name: String, struct_name: String, fields: String) {
-    writeln!(writer, "pub struct {struct_name} {{").unwrap();
-    for field in fields.split(", ") {
-        let field_str: Vec<&str> = field.split(" ").collect();
-        let field_type = field_str[0].to_string();
-        let field_name = field_str[1].to_string();
-        writeln!(writer, "    {field_name}: {field_type},").unwrap();
+// Parses `Binary : Expr left, Token operator, Expr right => "({operator} {left} {right})"`.
+fn parse_variant(line: &str) -> Result<Variant, String> {
+    let Some((name, rest)) = line.split_once(':') else {
+        return Err(format!("Expect ':' after variant name in '{line}'."));
+    };
+    let name = name.trim();
+    if !is_type_name(name) {
+        return Err(format!(
+            "Expect a variant name like 'Binary', got '{name}'."
+        ));
+    }
+    let Some((fields_str, display)) = rest.split_once("=>") else {
+        return Err(format!(
+            "Expect '=>' and the Display format after the fields of '{name}'."
+        ));
+    };
+    let mut fields: Vec<Field> = Vec::new();
+    for field in split_fields(fields_str) {
+        let field = field.trim();
+        let Some((ty, field_name)) = field.rsplit_once(' ') else {
+            return Err(format!(
+                "Expect type and name of a field of '{name}', got '{field}'."
+            ));
+        };
+        if !is_identifier(field_name) {
+            return Err(format!("Invalid field name '{field_name}' in '{name}'."));
+        }
+        if fields.iter().any(|f| f.name == field_name) {
+            return Err(format!("Duplicate field '{field_name}' in '{name}'."));
+        }
+        fields.push(Field {
+            ty: ty.trim().to_string(),
+            name: field_name.to_string(),
+        });
     }
-    writeln!(writer, "}}").unwrap();
+    let display = display.trim();
+    let Some(display) = display.strip_prefix('"').and_then(|d| d.strip_suffix('"')) else {
+        return Err(format!(
+            "Expect the Display format of '{name}' in double quotes."
+        ));
+    };
+    // Everything in braces has to be a field, otherwise the generated code won't compile.
+    for part in display.split('{').skip(1) {
+        let Some((placeholder, _)) = part.split_once('}') else {
+            return Err(format!("Unclosed '{{' in the Display format of '{name}'."));
+        };
+        if !fields.iter().any(|f| f.name == placeholder) {
+            return Err(format!(
+                "Unknown field '{placeholder}' in the Display format of '{name}'."
+            ));
+        }
+    }
+    Ok(Variant {
+        name: name.to_string(),
+        docs: Vec::new(),
+        fields,
+        display: display.to_string(),
+    })
+}
+
+pub fn define_ast(grammar: &Grammar) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by generate_ast from src/grammar.ast, do not edit by hand."
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    for use_line in &grammar.uses {
+        writeln!(out, "{use_line}").unwrap();
+    }
+    for base in &grammar.bases {
+        define_enum(&mut out, grammar, base);
+        define_display(&mut out, grammar, base);
+        define_visitor(&mut out, grammar, base, false);
+        define_visitor(&mut out, grammar, base, true);
+        define_fold(&mut out, grammar, base);
+    }
+    let displays_children = grammar.bases.iter().any(|base| {
+        base.variants.iter().any(|variant| {
+            variant.fields.iter().any(|field| {
+                matches!(
+                    grammar.field_kind(field),
+                    FieldKind::Children(_) | FieldKind::OptionalChild(_)
+                ) && displays_field(variant, field)
+            })
+        })
+    });
+    if displays_children {
+        define_spaced(&mut out);
+    }
+    writeln!(out, "\n#[cfg(test)]\nmod tests;").unwrap();
+    out
+}
+
+fn displays_field(variant: &Variant, field: &Field) -> bool {
+    variant.display.contains(&format!("{{{}}}", field.name))
+}
+
+// Display of the `Vec<Base>` and `Option<Base>` fields used in formats.
+fn define_spaced(out: &mut String) {
+    writeln!(
+        out,
+        "
+// Nodes each preceded by a space, for lists of children in the formats above.
+struct Spaced<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for Spaced<'_, T> {{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {{
+        for item in self.0 {{
+            write!(f, \" {{item}}\")?;
+        }}
+        Ok(())
+    }}
+}}"
+    )
+    .unwrap();
+}
+
+fn write_docs(out: &mut String, indent: &str, docs: &[String]) {
+    for doc in docs {
+        // rustfmt strips trailing whitespace of empty doc lines.
+        writeln!(out, "{}", format!("{indent}/// {doc}").trim_end()).unwrap();
+    }
+}
+
+fn define_enum(out: &mut String, grammar: &Grammar, base: &Base) {
+    writeln!(out).unwrap();
+    write_docs(out, "", &base.docs);
+    writeln!(out, "pub enum {} {{", base.name).unwrap();
+    let variants: Vec<EnumVariant> = base
+        .variants
+        .iter()
+        .map(|variant| {
+            let fields = variant
+                .fields
+                .iter()
+                .map(|field| match grammar.field_kind(field) {
+                    FieldKind::Child(base) => format!("{}: Box<{base}>", field.name),
+                    FieldKind::OptionalChild(base) => {
+                        format!("{}: Option<Box<{base}>>", field.name)
+                    }
+                    _ => format!("{}: {}", field.name, field.ty),
+                })
+                .collect();
+            (&variant.docs[..], &variant.name[..], fields)
+        })
+        .collect();
+    write_variants(out, &variants);
+    writeln!(out, "}}").unwrap();
+}
+
+// Doc comments, name and fields of a struct variant.
+type EnumVariant<'a> = (&'a [String], &'a str, Vec<String>);
+
+fn write_variants(out: &mut String, variants: &[EnumVariant]) {
+    let fits = |name: &str, fields: &[String]| {
+        let single_line = format!("    {name} {{ {} }},", fields.join(", "));
+        fields.join(", ").len() <= STRUCT_VARIANT_WIDTH && single_line.len() <= MAX_WIDTH
+    };
+    // rustfmt writes every variant over several lines when some variants take several lines,
+    // doc comments included, and others only one.
+    let one_line = |(docs, name, fields): &EnumVariant| docs.is_empty() && fits(name, fields);
+    let mixed = variants.iter().any(one_line) && !variants.iter().all(one_line);
+    for (docs, name, fields) in variants {
+        write_docs(out, "    ", docs);
+        if !mixed && fits(name, fields) {
+            writeln!(out, "    {name} {{ {} }},", fields.join(", ")).unwrap();
+            continue;
+        }
+        writeln!(out, "    {name} {{").unwrap();
+        for field in fields {
+            writeln!(out, "        {field},").unwrap();
+        }
+        writeln!(out, "    }},").unwrap();
+    }
+}
+
+fn define_display(out: &mut String, grammar: &Grammar, base: &Base) {
+    writeln!(out, "\nimpl fmt::Display for {} {{", base.name).unwrap();
+    writeln!(
+        out,
+        "    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {{"
+    )
+    .unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for variant in &base.variants {
+        let used: Vec<&Field> = variant
+            .fields
+            .iter()
+            .filter(|field| displays_field(variant, field))
+            .collect();
+        let mut bindings: Vec<&str> = used.iter().map(|f| f.name.as_str()).collect();
+        if used.len() < variant.fields.len() {
+            bindings.push("..");
+        }
+        let pattern = format!("{}::{}", base.name, variant.name);
+        let spaced: Vec<String> = used
+            .iter()
+            .filter_map(|field| match grammar.field_kind(field) {
+                FieldKind::Children(_) => Some(format!(", {0} = Spaced({0})", field.name)),
+                FieldKind::OptionalChild(_) => {
+                    Some(format!(", {0} = Spaced({0}.as_slice())", field.name))
+                }
+                _ => None,
+            })
+            .collect();
+        let body = format!("write!(f, \"{}\"{}),", variant.display, spaced.concat());
+        write_arm(out, 3, &pattern, &bindings, &body);
+    }
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+}
+
+// Writes a match arm destructuring a struct variant, formatted the way rustfmt would.
+fn write_arm(out: &mut String, depth: usize, pattern: &str, bindings: &[&str], body: &str) {
+    let indent = " ".repeat(4 * depth);
+    let fields = bindings.join(", ");
+    let single_line = format!("{indent}{pattern} {{ {fields} }} => {body}");
+    if fields.len() <= STRUCT_LIT_WIDTH && single_line.len() <= MAX_WIDTH {
+        writeln!(out, "{single_line}").unwrap();
+        return;
+    }
+    if fields.len() <= STRUCT_LIT_WIDTH {
+        // The pattern fits, rustfmt moves the body into a block instead.
+        let body = body.strip_suffix(',').unwrap_or(body);
+        writeln!(out, "{indent}{pattern} {{ {fields} }} => {{").unwrap();
+        writeln!(out, "{indent}    {body}\n{indent}}}").unwrap();
+        return;
+    }
+    writeln!(out, "{indent}{pattern} {{").unwrap();
+    for binding in bindings {
+        // rustfmt doesn't put a trailing comma after the rest pattern.
+        let comma = if *binding == ".." { "" } else { "," };
+        writeln!(out, "{indent}    {binding}{comma}").unwrap();
+    }
+    let last_line = format!("{indent}}} => {body}");
+    match split_call(body) {
+        Some((callee, args)) if last_line.len() > MAX_WIDTH => {
+            // rustfmt puts the arguments on their own lines.
+            writeln!(out, "{indent}}} => {callee}(").unwrap();
+            for (i, arg) in args.iter().enumerate() {
+                let comma = if i + 1 < args.len() { "," } else { "" };
+                writeln!(out, "{indent}    {arg}{comma}").unwrap();
+            }
+            writeln!(out, "{indent}),").unwrap();
+        }
+        _ => writeln!(out, "{last_line}").unwrap(),
+    }
+}
+
+// Splits `callee(a, b(c, d), "e, f"),` into the callee and its arguments.
+fn split_call(body: &str) -> Option<(&str, Vec<&str>)> {
+    let (callee, rest) = body.split_once('(')?;
+    let args = rest.strip_suffix("),")?;
+    let mut result = Vec::new();
+    let (mut depth, mut in_string, mut start) = (0, false, 0);
+    let mut chars = args.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '(' | '[' | '<' if !in_string => depth += 1,
+            ')' | ']' | '>' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                result.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(args[start..].trim());
+    Some((callee, result))
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+// Writes `fn name(params) body`, splitting parameters over lines if they don't fit.
+fn write_fn(out: &mut String, name: &str, params: &[String], body: &str) {
+    let single_line = format!("    fn {name}({}) {body}", params.join(", "));
+    if single_line.lines().next().unwrap().len() <= MAX_WIDTH {
+        writeln!(out, "{single_line}").unwrap();
+        return;
+    }
+    writeln!(out, "    fn {name}(").unwrap();
+    for param in params {
+        writeln!(out, "        {param},").unwrap();
+    }
+    writeln!(out, "    ) {body}").unwrap();
+}
+
+fn define_visitor(out: &mut String, grammar: &Grammar, base: &Base, mutable: bool) {
+    let name = &base.name;
+    let lower = snake_case(name);
+    let (suffix, reference) = if mutable { ("Mut", "&mut ") } else { ("", "&") };
+    let trait_name = format!("{name}Visitor{suffix}");
+    let walk_name = if mutable {
+        format!("walk_{lower}_mut")
+    } else {
+        format!("walk_{lower}")
+    };
+
+    // Visitors of other bases this one has children of, e.g. statements containing expressions.
+    let mut supertraits: Vec<String> = Vec::new();
+    for field in base.variants.iter().flat_map(|v| &v.fields) {
+        if let FieldKind::Child(child)
+        | FieldKind::Children(child)
+        | FieldKind::OptionalChild(child) = grammar.field_kind(field)
+        {
+            let supertrait = format!("{child}Visitor{suffix}");
+            if child != name && !supertraits.contains(&supertrait) {
+                supertraits.push(supertrait);
+            }
+        }
+    }
+    let supertraits = if supertraits.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", supertraits.join(" + "))
+    };
+
+    if mutable {
+        writeln!(
+            out,
+            "\n// Same as {name}Visitor, but for passes that rewrite the tree in place."
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            out,
+            "\n// Traversal of the tree for passes that only read it. Every visit method walks
+// the children by default, so a pass overrides only the nodes it cares about.
+// Override visit_{lower} to do something for every node, and call {walk_name}
+// from it to keep going down."
+        )
+        .unwrap();
+    }
+    writeln!(out, "pub trait {trait_name}{supertraits} {{").unwrap();
+    writeln!(
+        out,
+        "    fn visit_{lower}(&mut self, {lower}: {reference}{name}) {{\n        {walk_name}(self, {lower});\n    }}"
+    )
+    .unwrap();
+    for variant in &base.variants {
+        let mut params = vec!["&mut self".to_string()];
+        let mut body = Vec::new();
+        for field in &variant.fields {
+            let (ty, visit) = match grammar.field_kind(field) {
+                FieldKind::Child(child) => (
+                    child.to_string(),
+                    Some(format!("self.visit_{}({});", snake_case(child), field.name)),
+                ),
+                FieldKind::Children(child) => (
+                    if mutable {
+                        field.ty.clone()
+                    } else {
+                        format!("[{child}]")
+                    },
+                    Some(format!(
+                        "for {0} in {1} {{\n            self.visit_{0}({0});\n        }}",
+                        snake_case(child),
+                        field.name
+                    )),
+                ),
+                FieldKind::OptionalChild(child) => (
+                    format!("Option<{reference}{child}>"),
+                    Some(format!(
+                        "if let Some({0}) = {1} {{\n            self.visit_{0}({0});\n        }}",
+                        snake_case(child),
+                        field.name
+                    )),
+                ),
+                FieldKind::Data => (format!("{reference}{}", field.ty), None),
+            };
+            let ty = match grammar.field_kind(field) {
+                FieldKind::Child(_) | FieldKind::Children(_) => format!("{reference}{ty}"),
+                _ => ty,
+            };
+            let param_name = match visit {
+                Some(visit) => {
+                    body.push(visit);
+                    field.name.clone()
+                }
+                None => format!("_{}", field.name),
+            };
+            params.push(format!("{param_name}: {ty}"));
+        }
+        let body = if body.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{\n        {}\n    }}", body.join("\n        "))
+        };
+        write_fn(
+            out,
+            &format!("visit_{}", snake_case(&variant.name)),
+            &params,
+            &body,
+        );
+    }
+    writeln!(out, "}}").unwrap();
+
+    if !mutable {
+        writeln!(out, "\n// Dispatches to the visit method of the node kind.").unwrap();
+    } else {
+        writeln!(out).unwrap();
+    }
+    writeln!(
+        out,
+        "pub fn {walk_name}<V: {trait_name} + ?Sized>(visitor: &mut V, {lower}: {reference}{name}) {{"
+    )
+    .unwrap();
+    writeln!(out, "    match {lower} {{").unwrap();
+    let as_deref = if mutable { "as_deref_mut" } else { "as_deref" };
+    for variant in &base.variants {
+        let bindings: Vec<&str> = variant.fields.iter().map(|f| f.name.as_str()).collect();
+        // Optional children are passed as `Option<&Expr>` rather than `&Option<Box<Expr>>`.
+        let args: Vec<String> = variant
+            .fields
+            .iter()
+            .map(|field| match grammar.field_kind(field) {
+                FieldKind::OptionalChild(_) => format!("{}.{as_deref}()", field.name),
+                _ => field.name.clone(),
+            })
+            .collect();
+        let body = format!(
+            "visitor.visit_{}({}),",
+            snake_case(&variant.name),
+            args.join(", ")
+        );
+        write_arm(
+            out,
+            2,
+            &format!("{name}::{}", variant.name),
+            &bindings,
+            &body,
+        );
+    }
+    writeln!(out, "    }}\n}}").unwrap();
+}
+
+// Visitor whose methods return a value, without the default walk of the visitors above.
+fn define_fold(out: &mut String, grammar: &Grammar, base: &Base) {
+    let name = &base.name;
+    let lower = snake_case(name);
+    let trait_name = format!("{name}Fold");
+    writeln!(
+        out,
+        "
+// Traversal which produces a value for every node, e.g. an evaluator. There is no
+// default walk, a method gets the children of its node and calls fold_{lower} on those
+// it needs. The result may borrow from the tree, which lives for 'a."
+    )
+    .unwrap();
+    writeln!(out, "pub trait {trait_name}<'a, R> {{").unwrap();
+    for variant in &base.variants {
+        let mut params = vec!["&mut self".to_string()];
+        for field in &variant.fields {
+            let ty = match grammar.field_kind(field) {
+                FieldKind::Child(child) => format!("&'a {child}"),
+                FieldKind::Children(child) => format!("&'a [{child}]"),
+                FieldKind::OptionalChild(child) => format!("Option<&'a {child}>"),
+                FieldKind::Data => format!("&'a {}", field.ty),
+            };
+            params.push(format!("{}: {ty}", field.name));
+        }
+        write_fn(
+            out,
+            &format!("fold_{}", snake_case(&variant.name)),
+            &params,
+            "-> R;",
+        );
+    }
+    writeln!(out, "}}").unwrap();
+
+    let generics = format!("<'a, R, V: {trait_name}<'a, R> + ?Sized>");
+    let params = format!("visitor: &mut V, {lower}: &'a {name}");
+    let signature = format!("pub fn fold_{lower}{generics}({params}) -> R {{");
+    writeln!(out, "\n// Dispatches to the fold method of the node kind.").unwrap();
+    if signature.len() <= MAX_WIDTH {
+        writeln!(out, "{signature}").unwrap();
+    } else {
+        writeln!(out, "pub fn fold_{lower}{generics}(").unwrap();
+        writeln!(
+            out,
+            "    visitor: &mut V,\n    {lower}: &'a {name},\n) -> R {{"
+        )
+        .unwrap();
+    }
+    writeln!(out, "    match {lower} {{").unwrap();
+    for variant in &base.variants {
+        let bindings: Vec<&str> = variant.fields.iter().map(|f| f.name.as_str()).collect();
+        let args: Vec<String> = variant
+            .fields
+            .iter()
+            .map(|field| match grammar.field_kind(field) {
+                FieldKind::OptionalChild(_) => format!("{}.as_deref()", field.name),
+                _ => field.name.clone(),
+            })
+            .collect();
+        let body = format!(
+            "visitor.fold_{}({}),",
+            snake_case(&variant.name),
+            args.join(", ")
+        );
+        write_arm(
+            out,
+            2,
+            &format!("{name}::{}", variant.name),
+            &bindings,
+            &body,
+        );
+    }
+    writeln!(out, "    }}\n}}").unwrap();
+}
+
+// Writes a match arm whose body is a block of the given lines, formatted the way rustfmt would.
+fn write_block_arm(
+    out: &mut String,
+    depth: usize,
+    pattern: &str,
+    bindings: &[&str],
+    lines: &[String],
+) {
+    let indent = " ".repeat(4 * depth);
+    let fields = bindings.join(", ");
+    let single_line = format!("{indent}{pattern} {{ {fields} }} => {{");
+    if bindings.is_empty() {
+        writeln!(out, "{indent}{pattern} {{}} => {{").unwrap();
+    } else if fields.len() <= STRUCT_LIT_WIDTH && single_line.len() <= MAX_WIDTH {
+        writeln!(out, "{single_line}").unwrap();
+    } else {
+        writeln!(out, "{indent}{pattern} {{").unwrap();
+        for binding in bindings {
+            let comma = if *binding == ".." { "" } else { "," };
+            writeln!(out, "{indent}    {binding}{comma}").unwrap();
+        }
+        writeln!(out, "{indent}}} => {{").unwrap();
+    }
+    for line in lines.iter().flat_map(|line| line.lines()) {
+        writeln!(out, "{indent}    {line}").unwrap();
+    }
+    writeln!(out, "{indent}}}").unwrap();
+}
+
+// Struct literal `name { fields }`, over several lines when rustfmt would split it.
+fn struct_literal(name: &str, fields: &[&str]) -> String {
+    let joined = fields.join(", ");
+    if fields.is_empty() {
+        format!("{name} {{}}")
+    } else if joined.len() <= STRUCT_LIT_WIDTH {
+        format!("{name} {{ {joined} }}")
+    } else {
+        format!("{name} {{\n    {},\n}}", fields.join(",\n    "))
+    }
+}
+
+// Spans aren't stored in the nodes of the arena but next to them, the other data is stored
+// once in the arena and the nodes refer to it by id.
+fn is_span(field: &Field) -> bool {
+    field.ty == "Span"
+}
+
+// Id of a value stored in the arena, e.g. `TokenId` for a `Token`.
+fn data_id(ty: &str) -> String {
+    let mut name: String = ty.chars().filter(|c| c.is_alphanumeric()).collect();
+    // Primitive types are lowercase, `f64` gives `F64Id`.
+    name[..1].make_ascii_uppercase();
+    format!("{name}Id")
+}
+
+// Types of the data fields stored in the arena, in the order they appear in the grammar.
+fn data_types(grammar: &Grammar) -> Vec<&str> {
+    let mut types = Vec::new();
+    for field in grammar
+        .bases
+        .iter()
+        .flat_map(|b| &b.variants)
+        .flat_map(|v| &v.fields)
+    {
+        let is_data = matches!(grammar.field_kind(field), FieldKind::Data);
+        if is_data && !is_span(field) && !types.contains(&field.ty.as_str()) {
+            types.push(field.ty.as_str());
+        }
+    }
+    types
+}
+
+// Arena version of the AST, written to src/arena/nodes.rs. Nodes are Copy and refer to their
+// children by id, `Vec<Base>` fields are ranges of ids stored next to each other.
+pub fn define_arena(grammar: &Grammar) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by generate_ast from src/grammar.ast, do not edit by hand."
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    for use_line in &grammar.uses {
+        // The arena only re-exports its own items.
+        writeln!(out, "{}", use_line.strip_prefix("pub ").unwrap_or(use_line)).unwrap();
+    }
+    let bases: Vec<&str> = grammar.bases.iter().map(|b| b.name.as_str()).collect();
+    let bases = match bases[..] {
+        [base] => base.to_string(),
+        _ => format!("{{{}}}", bases.join(", ")),
+    };
+    writeln!(
+        out,
+        "\nuse super::{{next_id, Covering}};\nuse crate::ast::{bases};"
+    )
+    .unwrap();
+
+    for base in &grammar.bases {
+        let name = &base.name;
+        writeln!(
+            out,
+            "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct {name}Id(u32);
+
+impl {name}Id {{
+    pub fn index(self) -> usize {{
+        self.0 as usize
+    }}
+}}
+
+// Children of a node with any number of them, e.g. list elements or map entries.
+// They are kept next to each other in the arena so that nodes stay Copy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct {name}List {{
+    start: u32,
+    len: u32,
+}}"
+        )
+        .unwrap();
+    }
+    let data = data_types(grammar);
+    for ty in &data {
+        writeln!(
+            out,
+            "\n#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]\npub struct {}(u32);",
+            data_id(ty)
+        )
+        .unwrap();
+    }
+
+    for base in &grammar.bases {
+        let variants: Vec<EnumVariant> = base
+            .variants
+            .iter()
+            .map(|variant| {
+                let fields = variant
+                    .fields
+                    .iter()
+                    .filter(|field| !is_span(field))
+                    .map(|field| match grammar.field_kind(field) {
+                        FieldKind::Child(base) => format!("{}: {base}Id", field.name),
+                        FieldKind::Children(base) => format!("{}: {base}List", field.name),
+                        FieldKind::OptionalChild(base) => {
+                            format!("{}: Option<{base}Id>", field.name)
+                        }
+                        FieldKind::Data => format!("{}: {}", field.name, data_id(&field.ty)),
+                    })
+                    .collect();
+                (&[][..], &variant.name[..], fields)
+            })
+            .collect();
+        writeln!(
+            out,
+            "\n// Same variants as {0}, spans are kept in the arena.\n#[derive(Copy, Clone, Debug)]\npub enum Arena{0} {{",
+            base.name
+        )
+        .unwrap();
+        write_variants(&mut out, &variants);
+        writeln!(out, "}}").unwrap();
+    }
+
+    writeln!(
+        out,
+        "\n// Nodes of every kind with their spans, and the data they refer to.\n#[derive(Default)]\npub struct Arena {{"
+    )
+    .unwrap();
+    for base in &grammar.bases {
+        let (name, lower) = (&base.name, snake_case(&base.name));
+        writeln!(out, "    {lower}s: Vec<Arena{name}>,").unwrap();
+        writeln!(out, "    {lower}_spans: Vec<Span>,").unwrap();
+        writeln!(out, "    {lower}_lists: Vec<{name}Id>,").unwrap();
+    }
+    for ty in &data {
+        writeln!(
+            out,
+            "    {}s: Vec<{ty}>,",
+            snake_case(&data_id(ty)[..data_id(ty).len() - 2])
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    writeln!(
+        out,
+        "\nimpl Arena {{\n    pub fn new() -> Self {{\n        Self::default()\n    }}"
+    )
+    .unwrap();
+    for base in &grammar.bases {
+        let (name, lower) = (&base.name, snake_case(&base.name));
+        writeln!(
+            out,
+            "    pub fn alloc_{lower}(&mut self, node: Arena{name}, span: Span) -> {name}Id {{
+        let id = {name}Id(next_id(&self.{lower}s));
+        self.{lower}s.push(node);
+        self.{lower}_spans.push(span);
+        id
+    }}
+    pub fn {lower}(&self, id: {name}Id) -> Arena{name} {{
+        self.{lower}s[id.index()]
+    }}
+    pub fn {lower}_span(&self, id: {name}Id) -> Span {{
+        self.{lower}_spans[id.index()]
+    }}
+    pub fn {lower}_count(&self) -> usize {{
+        self.{lower}s.len()
+    }}
+    // Ids of all the nodes, children come before their parents.
+    pub fn {lower}_ids(&self) -> impl Iterator<Item = {name}Id> {{
+        (0..next_id(&self.{lower}s)).map({name}Id)
+    }}
+    pub fn add_{lower}_list(&mut self, ids: &[{name}Id]) -> {name}List {{
+        let start = next_id(&self.{lower}_lists);
+        self.{lower}_lists.extend_from_slice(ids);
+        {name}List {{
+            start,
+            len: next_id(ids),
+        }}
+    }}
+    pub fn {lower}_list(&self, list: {name}List) -> &[{name}Id] {{
+        let start = list.start as usize;
+        &self.{lower}_lists[start..start + list.len as usize]
+    }}"
+        )
+        .unwrap();
+    }
+    for ty in &data {
+        let id = data_id(ty);
+        let lower = snake_case(&id[..id.len() - 2]);
+        writeln!(
+            out,
+            "    pub fn add_{lower}(&mut self, {lower}: {ty}) -> {id} {{
+        let id = {id}(next_id(&self.{lower}s));
+        self.{lower}s.push({lower});
+        id
+    }}
+    pub fn {lower}(&self, id: {id}) -> &{ty} {{
+        &self.{lower}s[id.0 as usize]
+    }}"
+        )
+        .unwrap();
+    }
+    for base in &grammar.bases {
+        define_lower(&mut out, grammar, base);
+    }
+    writeln!(out, "}}").unwrap();
+
+    let mut uses_spaced = false;
+    for base in &grammar.bases {
+        uses_spaced |= define_arena_display(&mut out, grammar, base);
+    }
+    if uses_spaced {
+        writeln!(
+            out,
+            "
+// Items each preceded by a space, for lists of children in the formats above.
+struct Spaced<I>(I);
+
+impl<I> fmt::Display for Spaced<I>
+where
+    I: Iterator + Clone,
+    I::Item: fmt::Display,
+{{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {{
+        for item in self.0.clone() {{
+            write!(f, \" {{item}}\")?;
+        }}
+        Ok(())
+    }}
+}}"
+        )
+        .unwrap();
+    }
+    out
+}
+
+// `lower_expr` and the like, copying a boxed tree into the arena.
+fn define_lower(out: &mut String, grammar: &Grammar, base: &Base) {
+    let (name, lower) = (&base.name, snake_case(&base.name));
+    writeln!(
+        out,
+        "
+    // Copies the boxed tree into the arena, returns the id of its root. The span of a node
+    // covers its tokens, its spans and its children.
+    pub fn lower_{lower}(&mut self, {lower}: &{name}) -> {name}Id {{
+        let mut covering = Covering::default();
+        let node = match {lower} {{"
+    )
+    .unwrap();
+    for variant in &base.variants {
+        let mut lines = Vec::new();
+        for field in &variant.fields {
+            let field_name = &field.name;
+            match grammar.field_kind(field) {
+                FieldKind::Child(child) => {
+                    let child = snake_case(child);
+                    lines.push(format!(
+                        "let {field_name} = self.lower_{child}({field_name});"
+                    ));
+                    lines.push(format!("covering.add(self.{child}_span({field_name}));"));
+                }
+                FieldKind::Children(child) => {
+                    let child = snake_case(child);
+                    lines.push(format!(
+                        "let mut ids = Vec::with_capacity({field_name}.len());
+for node in {field_name} {{
+    let id = self.lower_{child}(node);
+    covering.add(self.{child}_span(id));
+    ids.push(id);
+}}
+let {field_name} = self.add_{child}_list(&ids);"
+                    ));
+                }
+                FieldKind::OptionalChild(child) => {
+                    let child = snake_case(child);
+                    lines.push(format!(
+                        "let {field_name} = {field_name}.as_deref().map(|node| self.lower_{child}(node));
+if let Some(id) = {field_name} {{
+    covering.add(self.{child}_span(id));
+}}"
+                    ));
+                }
+                FieldKind::Data if is_span(field) => {
+                    lines.push(format!("covering.add(*{field_name});"));
+                }
+                FieldKind::Data => {
+                    if field.ty == "Token" {
+                        lines.push(format!("covering.add(Span::from_token({field_name}));"));
+                    }
+                    let id = data_id(&field.ty);
+                    let data = snake_case(&id[..id.len() - 2]);
+                    lines.push(format!(
+                        "let {field_name} = self.add_{data}({field_name}.clone());"
+                    ));
+                }
+            }
+        }
+        let fields: Vec<&str> = variant
+            .fields
+            .iter()
+            .filter(|field| !is_span(field))
+            .map(|field| field.name.as_str())
+            .collect();
+        lines.push(struct_literal(
+            &format!("Arena{name}::{}", variant.name),
+            &fields,
+        ));
+        let bindings: Vec<&str> = variant.fields.iter().map(|f| f.name.as_str()).collect();
+        write_block_arm(
+            out,
+            3,
+            &format!("{name}::{}", variant.name),
+            &bindings,
+            &lines,
+        );
+    }
+    writeln!(
+        out,
+        "        }};\n        self.alloc_{lower}(node, covering.span())\n    }}"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "
+    // Same output as the Display of {name}.
+    pub fn display_{lower}(&self, id: {name}Id) -> Display{name}<'_> {{
+        Display{name} {{ arena: self, id }}
+    }}"
+    )
+    .unwrap();
+}
+
+// Display of the nodes of the arena, returns whether it uses Spaced.
+fn define_arena_display(out: &mut String, grammar: &Grammar, base: &Base) -> bool {
+    let (name, lower) = (&base.name, snake_case(&base.name));
+    writeln!(
+        out,
+        "
+#[derive(Copy, Clone)]
+pub struct Display{name}<'a> {{
+    arena: &'a Arena,
+    id: {name}Id,
+}}
+
+impl fmt::Display for Display{name}<'_> {{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {{
+        let arena = self.arena;
+        match arena.{lower}(self.id) {{"
+    )
+    .unwrap();
+    let mut uses_spaced = false;
+    for variant in &base.variants {
+        let mut bindings = Vec::new();
+        let mut lines = Vec::new();
+        for field in variant.fields.iter().filter(|field| !is_span(field)) {
+            if !displays_field(variant, field) {
+                continue;
+            }
+            let field_name = &field.name;
+            bindings.push(field_name.as_str());
+            lines.push(match grammar.field_kind(field) {
+                FieldKind::Child(child) => {
+                    format!("let {field_name} = arena.display_{}({field_name});", snake_case(child))
+                }
+                FieldKind::Children(child) => {
+                    uses_spaced = true;
+                    let child = snake_case(child);
+                    format!(
+                        "let {field_name} = arena.{child}_list({field_name}).iter();
+let {field_name} = Spaced({field_name}.map(|&id| arena.display_{child}(id)));"
+                    )
+                }
+                FieldKind::OptionalChild(child) => {
+                    uses_spaced = true;
+                    format!(
+                        "let {field_name} = Spaced({field_name}.map(|id| arena.display_{}(id)).into_iter());",
+                        snake_case(child)
+                    )
+                }
+                FieldKind::Data => {
+                    let id = data_id(&field.ty);
+                    let data = snake_case(&id[..id.len() - 2]);
+                    format!("let {field_name} = arena.{data}({field_name});")
+                }
+            });
+        }
+        let stored = variant
+            .fields
+            .iter()
+            .filter(|field| !is_span(field))
+            .count();
+        if bindings.len() < stored {
+            bindings.push("..");
+        }
+        lines.push(format!("write!(f, \"{}\")", variant.display));
+        write_block_arm(
+            out,
+            3,
+            &format!("Arena{name}::{}", variant.name),
+            &bindings,
+            &lines,
+        );
+    }
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+    uses_spaced
+}
+
+// Markdown reference of the AST, for people rather than the compiler.
+pub fn define_reference(grammar: &Grammar) -> String {
+    let mut out = String::new();
+    writeln!(out, "# AST reference\n").unwrap();
+    writeln!(
+        out,
+        "Generated by generate_ast from src/grammar.ast, do not edit by hand."
+    )
+    .unwrap();
+    for base in &grammar.bases {
+        writeln!(out, "\n## {}\n", base.name).unwrap();
+        if !base.docs.is_empty() {
+            writeln!(out, "{}\n", base.docs.join("\n")).unwrap();
+        }
+        for variant in &base.variants {
+            writeln!(out, "### {}\n", variant.name).unwrap();
+            if !variant.docs.is_empty() {
+                writeln!(out, "{}\n", variant.docs.join("\n")).unwrap();
+            }
+            writeln!(out, "| Field | Type |\n| --- | --- |").unwrap();
+            for field in &variant.fields {
+                let ty = match grammar.field_kind(field) {
+                    FieldKind::Child(base)
+                    | FieldKind::Children(base)
+                    | FieldKind::OptionalChild(base) => {
+                        // Link to the section of the base.
+                        let anchor = base.to_lowercase();
+                        field.ty.replace(base, &format!("[{base}](#{anchor})"))
+                    }
+                    FieldKind::Data => format!("`{}`", field.ty),
+                };
+                writeln!(out, "| `{}` | {ty} |", field.name).unwrap();
+            }
+            writeln!(out, "\nPrinted as `{}`.\n", variant.display).unwrap();
+        }
+    }
+    // No empty line at the end of the file.
+    out.truncate(out.trim_end().len() + 1);
+    out
 }
 
 #[allow(dead_code)]
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     match args.len() {
-        2 => {
-            let output_fpath = args[1].clone();
-            println!("Output fpath: {}", output_fpath);
-
-            let grammar: Vec<String> = vec![
-                String::from("Binary   : Expr left, Token operator, Expr right"),
-                String::from("Grouping : Expr expression"),
-                String::from("Literal  : Object value"),
-                String::from("Unary    : Token operator, Expr right"),
-            ];
-            define_ast(output_fpath, String::from("Expr"), grammar);
-        }
-        _ => println!("Usage: generate_ast [output_file]"),
+        5 => {
+            let grammar_fpath = &args[1];
+            let ast_fpath = &args[2];
+            let arena_fpath = &args[3];
+            let reference_fpath = &args[4];
+            let source = fs::read_to_string(grammar_fpath).expect("Could not read the grammar.");
+            let grammar = match parse_grammar(&source) {
+                Ok(grammar) => grammar,
+                Err(err) => {
+                    eprintln!("{grammar_fpath}, {err}");
+                    std::process::exit(1);
+                }
+            };
+            fs::write(ast_fpath, define_ast(&grammar)).expect("Could not write the AST.");
+            fs::write(arena_fpath, define_arena(&grammar)).expect("Could not write the arena.");
+            fs::write(reference_fpath, define_reference(&grammar))
+                .expect("Could not write the reference.");
+            println!(
+                "Generated {ast_fpath}, {arena_fpath} and {reference_fpath} from {grammar_fpath}"
+            );
+        }
+        _ => {
+            println!("Usage: generate_ast [grammar_file] [ast_file] [arena_file] [reference_file]")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ast_is_up_to_date() {
+        let grammar = parse_grammar(include_str!("grammar.ast")).unwrap();
+        // Don't print the whole file if they differ.
+        assert!(
+            define_ast(&grammar) == include_str!("ast.rs"),
+            "src/ast.rs is out of date, run `just ast`."
+        );
+        assert!(
+            define_arena(&grammar) == include_str!("arena/nodes.rs"),
+            "src/arena/nodes.rs is out of date, run `just ast`."
+        );
+        assert!(
+            define_reference(&grammar) == include_str!("../docs/ast.md"),
+            "docs/ast.md is out of date, run `just ast`."
+        );
+    }
+
+    fn error(source: &str) -> String {
+        match parse_grammar(source) {
+            Ok(_) => panic!("Expected an error for {source:?}"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_grammar_errors() {
+        assert_eq!(
+            error("Expr\n  Binary Expr left => \"\""),
+            "line 2: Expect ':' after variant name in 'Binary Expr left => \"\"'."
+        );
+        assert_eq!(
+            error("  Unary : Expr right => \"{right}\""),
+            "line 1: Variant 'Unary' should come after its base, e.g. 'Expr'."
+        );
+        assert_eq!(
+            error("Expr\n  Unary : Expr right"),
+            "line 2: Expect '=>' and the Display format after the fields of 'Unary'."
+        );
+        assert_eq!(
+            error("Expr\n  Unary : Expr right => {right}"),
+            "line 2: Expect the Display format of 'Unary' in double quotes."
+        );
+        assert_eq!(
+            error("Expr\n  Unary : Expr => \"\""),
+            "line 2: Expect type and name of a field of 'Unary', got 'Expr'."
+        );
+        assert_eq!(
+            error("Expr\n  Unary : Expr right => \"{left}\""),
+            "line 2: Unknown field 'left' in the Display format of 'Unary'."
+        );
+        assert_eq!(
+            error("Expr\n\n/// Dangling doc.\n"),
+            "line 3: Expect a base or a variant after the doc comment."
+        );
+    }
+
+    #[test]
+    fn test_generic_fields() {
+        let grammar =
+            parse_grammar("Expr\n  Map : HashMap<String, Expr> entries => \"map\"").unwrap();
+        let field = &grammar.bases[0].variants[0].fields[0];
+        assert_eq!(field.ty, "HashMap<String, Expr>");
+        assert_eq!(field.name, "entries");
+    }
+
+    #[test]
+    fn test_optional_children() {
+        let grammar = parse_grammar(concat!(
+            "Expr\n  Literal : f64 value => \"{value}\"\n",
+            "Stmt\n",
+            "  If : Expr condition, Stmt then_branch, Option<Stmt> else_branch => \"(if{else_branch})\"\n",
+            "  Return : Option<Box<Expr>> value => \"(return{value})\"",
+        ))
+        .unwrap();
+        let kinds: Vec<_> = grammar.bases[1]
+            .variants
+            .iter()
+            .flat_map(|variant| &variant.fields)
+            .map(|field| grammar.field_kind(field))
+            .collect();
+        assert!(matches!(
+            kinds[..],
+            [
+                FieldKind::Child("Expr"),
+                FieldKind::Child("Stmt"),
+                FieldKind::OptionalChild("Stmt"),
+                FieldKind::OptionalChild("Expr"),
+            ]
+        ));
+        let ast = define_ast(&grammar);
+        for line in [
+            "        else_branch: Option<Box<Stmt>>,",
+            "    Return {\n        value: Option<Box<Expr>>,\n    },",
+            "    fn visit_return(&mut self, value: Option<&Expr>) {",
+            "        if let Some(expr) = value {",
+            "        } => visitor.visit_if(condition, then_branch, else_branch.as_deref()),",
+            "        Stmt::Return { value } => visitor.visit_return(value.as_deref_mut()),",
+            "    fn fold_return(&mut self, value: Option<&'a Expr>) -> R;",
+            "        Stmt::Return { value } => visitor.fold_return(value.as_deref()),",
+            "                write!(f, \"(return{value})\", value = Spaced(value.as_slice()))",
+        ] {
+            assert!(ast.contains(line), "{line:?} is missing from:\n{ast}");
+        }
+        assert!(define_reference(&grammar).contains("| `else_branch` | Option<[Stmt](#stmt)> |"));
+        let arena = define_arena(&grammar);
+        for line in [
+            "        else_branch: Option<StmtId>,",
+            "                let value = value.as_deref().map(|node| self.lower_expr(node));",
+            "                if let Some(id) = value {",
+            "                let value = Spaced(value.map(|id| arena.display_expr(id)).into_iter());",
+            "pub struct F64Id(u32);",
+        ] {
+            assert!(arena.contains(line), "{line:?} is missing from:\n{arena}");
+        }
     }
 }
@@ -1,20 +1,51 @@
+pub mod arena;
 pub mod ast;
+pub mod call_stack;
+pub mod dump;
 pub mod gen_ast;
+pub mod map_key;
+pub mod native;
+pub mod numeric;
 pub mod parser;
 pub mod scanner;
+pub mod span;
 pub mod types;
+pub mod value;
 
 use ast::Expr;
+use dump::{AstFormat, TokenFormat};
 use parser::Parser;
 use scanner::{ErrorHandler, Scanner};
 
-pub fn run(line: &str, err_handler: &mut ErrorHandler) {
+// Scans and parses the source, errors are reported to the handler.
+pub fn parse(line: &str, err_handler: &mut ErrorHandler) -> Option<Expr> {
     let mut scanner = Scanner::new(line, err_handler);
     scanner.scan_tokens();
+    let tokens = scanner.tokens;
+
+    let mut parser: Parser = Parser::new(tokens, err_handler);
+    let expression = parser.parse().ok()?;
+    if err_handler.has_error {
+        return None;
+    }
+    Some(expression)
+}
 
-    let parser: Parser = Parser::new(scanner.tokens);
+pub fn run(line: &str, err_handler: &mut ErrorHandler) {
     //TODO: make this a bit more useful.
-    let expression: Expr = parser.parse().expect("Parser encountered errors!");
+    if let Some(expression) = parse(line, err_handler) {
+        println!("{}", expression);
+    }
+}
+
+pub fn print_tokens(line: &str, err_handler: &mut ErrorHandler, format: TokenFormat) {
+    let mut scanner = Scanner::new(line, err_handler);
+    scanner.scan_tokens();
+    print!("{}", dump::dump_tokens(&scanner.tokens, format));
+}
 
-    println!("{}", expression);
+pub fn print_ast(line: &str, err_handler: &mut ErrorHandler, format: AstFormat) {
+    if let Some(expression) = parse(line, err_handler) {
+        print!("{}", dump::dump_ast(&expression, format));
+    }
 }
@@ -1,22 +1,216 @@
 use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::process;
 
+use tlox::dump::{AstFormat, TokenFormat};
 use tlox::scanner::ErrorHandler;
 
 static INPUT_RELATED_EXIT_CODE: i32 = 65;
+static NO_INPUT_EXIT_CODE: i32 = 66;
+static USAGE_EXIT_CODE: i32 = 64;
 
-fn run_file(filepath: &str) {
-    let fcontent = fs::read_to_string(filepath).expect("Could not load a file {filepath}");
+const HELP: &str = "\
+Usage: tlox [command] [options] [script | - | -e code] [args...]
+
+Commands:
+  run     Run the script, the default command
+  repl    Start the interactive prompt, what running without a script does too
+  check   Report the errors of the script without running it
+  tokens  Print the tokens of the script, --format=text|json
+  ast     Print the syntax tree of the script, --format=sexpr|tree|json|dot
+
+Without a script, run, tokens and ast read lines from an interactive prompt.
+
+Options:
+  -e code            Use code instead of a script
+  -                  Read the script from stdin
+  --                 End of the options, for scripts whose path starts with a dash
+  --tokens[=FORMAT]  Same as the tokens command with --format=FORMAT
+  --ast[=FORMAT]     Same as the ast command with --format=FORMAT
+  -h, --help         Print this help
+  -V, --version      Print the version
+
+The arguments after the script are the script's, args() will return them.
+";
+
+// What to do with the source once we have it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Run,
+    Check,
+    Tokens(TokenFormat),
+    Ast(AstFormat),
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    File(String),
+    Stdin,
+    Code(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Version,
+    Repl(Mode),
+    Source {
+        mode: Mode,
+        input: Input,
+        script_args: Vec<String>,
+    },
+}
+
+// Parses the arguments without the program name, errors are usage errors.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+    let mut command = match args.peek().map(String::as_str) {
+        Some("-h" | "--help") => return Ok(Command::Help),
+        Some("-V" | "--version") => return Ok(Command::Version),
+        Some(name @ ("run" | "repl" | "check" | "tokens" | "ast")) => {
+            let name = name.to_string();
+            args.next();
+            name
+        }
+        // `tlox script.lox` runs the script.
+        _ => "run".to_string(),
+    };
+    let mut mode = match command.as_str() {
+        "check" => Mode::Check,
+        "tokens" => Mode::Tokens(TokenFormat::Text),
+        "ast" => Mode::Ast(AstFormat::Sexpr),
+        _ => Mode::Run,
+    };
+    // Options come before the input, everything after it is the script's.
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-" => input = Some(Input::Stdin),
+            "--" => match args.next() {
+                Some(path) => input = Some(Input::File(path)),
+                None => return Err("Expect a script after --.".to_string()),
+            },
+            "-e" => match args.next() {
+                Some(code) => input = Some(Input::Code(code)),
+                None => return Err("Expect code after -e.".to_string()),
+            },
+            _ => {
+                if let Some((name, format)) = dump_alias(&arg) {
+                    if command != "run" {
+                        return Err(format!("Option '{arg}' doesn't go with {command}."));
+                    }
+                    mode = match name {
+                        "tokens" => TokenFormat::from_flag(format).map(Mode::Tokens),
+                        _ => AstFormat::from_flag(format).map(Mode::Ast),
+                    }
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown format '{}' for {name}.",
+                            format.unwrap_or_default()
+                        )
+                    })?;
+                    command = name.to_string();
+                    continue;
+                }
+                if let Some(format) = arg.strip_prefix("--format=") {
+                    mode = match mode {
+                        Mode::Tokens(_) => TokenFormat::from_flag(Some(format)).map(Mode::Tokens),
+                        Mode::Ast(_) => AstFormat::from_flag(Some(format)).map(Mode::Ast),
+                        _ => None,
+                    }
+                    .ok_or_else(|| format!("Unknown format '{format}' for {command}."))?;
+                    continue;
+                }
+                if arg.starts_with('-') {
+                    return Err(format!("Unknown option '{arg}'."));
+                }
+                input = Some(Input::File(arg));
+            }
+        }
+        if input.is_some() {
+            break;
+        }
+    }
+    let script_args: Vec<String> = args.collect();
+    if let Some(option) = script_args.first().filter(|arg| arg.starts_with('-')) {
+        if mode != Mode::Run {
+            return Err(format!(
+                "Options go before the script, '{option}' is after it."
+            ));
+        }
+    }
+    if mode != Mode::Run && !script_args.is_empty() {
+        return Err(format!(
+            "Only scripts which run take arguments, not {command}."
+        ));
+    }
+    match (command.as_str(), input) {
+        ("repl", None) => Ok(Command::Repl(Mode::Run)),
+        ("repl", Some(_)) => Err("The REPL doesn't take a script.".to_string()),
+        ("check", None) => Err("Expect a script to check.".to_string()),
+        (_, None) => Ok(Command::Repl(mode)),
+        (_, Some(input)) => Ok(Command::Source {
+            mode,
+            input,
+            script_args,
+        }),
+    }
+}
+
+// `--tokens[=FORMAT]` and `--ast[=FORMAT]`, the options from before there were commands.
+// Returns the command and the format if any.
+fn dump_alias(arg: &str) -> Option<(&str, Option<&str>)> {
+    let option = arg.strip_prefix("--")?;
+    let (name, format) = match option.split_once('=') {
+        Some((name, format)) => (name, Some(format)),
+        None => (option, None),
+    };
+    matches!(name, "tokens" | "ast").then_some((name, format))
+}
+
+fn run(source: &str, mode: Mode, err_handler: &mut ErrorHandler) {
+    match mode {
+        Mode::Run => tlox::run(source, err_handler),
+        // TODO: resolve too, once there is a resolver.
+        Mode::Check => {
+            tlox::parse(source, err_handler);
+        }
+        Mode::Tokens(format) => tlox::print_tokens(source, err_handler, format),
+        Mode::Ast(format) => tlox::print_ast(source, err_handler, format),
+    }
+}
+
+fn read_source(input: Input) -> Result<String, String> {
+    match input {
+        Input::File(path) => {
+            fs::read_to_string(&path).map_err(|err| format!("Could not read {path}: {err}."))
+        }
+        Input::Stdin => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|err| format!("Could not read stdin: {err}."))?;
+            Ok(source)
+        }
+        Input::Code(code) => Ok(code),
+    }
+}
+
+fn run_source(input: Input, mode: Mode) {
+    let source = read_source(input).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        process::exit(NO_INPUT_EXIT_CODE);
+    });
     let mut err_handler = ErrorHandler::new();
-    tlox::run(&fcontent, &mut err_handler);
+    run(&source, mode, &mut err_handler);
     if err_handler.has_error {
         process::exit(INPUT_RELATED_EXIT_CODE);
     }
 }
 
-fn run_prompt() {
+fn run_prompt(mode: Mode) {
     let mut err_handler = ErrorHandler::new();
     loop {
         print!("> ");
@@ -29,17 +223,150 @@ fn run_prompt() {
         if buf.trim().is_empty() {
             break;
         }
-        tlox::run(&buf, &mut err_handler);
+        run(&buf, mode, &mut err_handler);
         err_handler.reset();
     }
 }
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        // 0th arg is always the program name.
-        1 => run_prompt(),
-        2 => run_file(&args[1]),
-        _ => println!("Usage: tlox [script]"),
+    // 0th arg is always the program name.
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => print!("{HELP}"),
+        Ok(Command::Version) => println!("tlox {}", env!("CARGO_PKG_VERSION")),
+        Ok(Command::Repl(mode)) => run_prompt(mode),
+        // TODO: give the script its arguments in a native::Context, once there is an interpreter
+        // to call natives with it.
+        Ok(Command::Source {
+            mode,
+            input,
+            script_args: _,
+        }) => run_source(input, mode),
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!("Usage: tlox [command] [options] [script | - | -e code] [args...]");
+            eprintln!("Run tlox --help for more.");
+            process::exit(USAGE_EXIT_CODE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn source(mode: Mode, input: Input, script_args: &[&str]) -> Result<Command, String> {
+        Ok(Command::Source {
+            mode,
+            input,
+            script_args: script_args.iter().map(|arg| arg.to_string()).collect(),
+        })
+    }
+
+    fn file(path: &str) -> Input {
+        Input::File(path.to_string())
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(parse(&[]), Ok(Command::Repl(Mode::Run)));
+        assert_eq!(parse(&["repl"]), Ok(Command::Repl(Mode::Run)));
+        assert_eq!(parse(&["a.lox"]), source(Mode::Run, file("a.lox"), &[]));
+        assert_eq!(
+            parse(&["run", "a.lox"]),
+            source(Mode::Run, file("a.lox"), &[])
+        );
+        assert_eq!(
+            parse(&["check", "-"]),
+            source(Mode::Check, Input::Stdin, &[])
+        );
+        assert_eq!(
+            parse(&["ast", "--format=json", "a.lox"]),
+            source(Mode::Ast(AstFormat::Json), file("a.lox"), &[])
+        );
+        assert_eq!(
+            parse(&["tokens"]),
+            Ok(Command::Repl(Mode::Tokens(TokenFormat::Text)))
+        );
+        assert_eq!(parse(&["--version"]), Ok(Command::Version));
+        // The options from before the commands still work.
+        assert_eq!(
+            parse(&["--tokens"]),
+            Ok(Command::Repl(Mode::Tokens(TokenFormat::Text)))
+        );
+        assert_eq!(
+            parse(&["--tokens=json", "a.lox"]),
+            source(Mode::Tokens(TokenFormat::Json), file("a.lox"), &[])
+        );
+        assert_eq!(
+            parse(&["--ast", "-"]),
+            source(Mode::Ast(AstFormat::Sexpr), Input::Stdin, &[])
+        );
+        assert_eq!(
+            parse(&["run", "--ast=dot", "a.lox"]),
+            source(Mode::Ast(AstFormat::Dot), file("a.lox"), &[])
+        );
+        assert_eq!(parse(&["check", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_script_args() {
+        assert_eq!(
+            parse(&["a.lox", "x", "--y"]),
+            source(Mode::Run, file("a.lox"), &["x", "--y"])
+        );
+        assert_eq!(
+            parse(&["run", "--", "-a.lox", "-"]),
+            source(Mode::Run, file("-a.lox"), &["-"])
+        );
+        assert_eq!(
+            parse(&["-e", "1 + 2", "x"]),
+            source(Mode::Run, Input::Code("1 + 2".to_string()), &["x"])
+        );
+        // A script named like a command needs a path.
+        assert_eq!(parse(&["./ast"]), source(Mode::Run, file("./ast"), &[]));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        let error = |args: &[&str]| parse(args).unwrap_err();
+        assert_eq!(error(&["--token"]), "Unknown option '--token'.");
+        assert_eq!(error(&["--ast=xml"]), "Unknown format 'xml' for ast.");
+        assert_eq!(
+            error(&["check", "--tokens", "a.lox"]),
+            "Option '--tokens' doesn't go with check."
+        );
+        assert_eq!(
+            error(&["--tokens", "--ast"]),
+            "Option '--ast' doesn't go with tokens."
+        );
+        assert_eq!(
+            error(&["--ast", "a.lox", "x"]),
+            "Only scripts which run take arguments, not ast."
+        );
+        assert_eq!(error(&["-e"]), "Expect code after -e.");
+        assert_eq!(
+            error(&["ast", "--format=xml"]),
+            "Unknown format 'xml' for ast."
+        );
+        assert_eq!(
+            error(&["run", "--format=json"]),
+            "Unknown format 'json' for run."
+        );
+        assert_eq!(error(&["check"]), "Expect a script to check.");
+        assert_eq!(error(&["--"]), "Expect a script after --.");
+        assert_eq!(error(&["run", "--"]), "Expect a script after --.");
+        assert_eq!(
+            error(&["ast", "a.lox", "--format=json"]),
+            "Options go before the script, '--format=json' is after it."
+        );
+        assert_eq!(error(&["repl", "a.lox"]), "The REPL doesn't take a script.");
+        assert_eq!(
+            error(&["check", "a.lox", "x"]),
+            "Only scripts which run take arguments, not check."
+        );
     }
 }
@@ -0,0 +1,108 @@
+// Keys of Lox maps.
+//
+// Two keys are the same entry when the values are equal in Lox, whatever their representation:
+// numbers are compared by value, so `Integer(1)` and `Float(1.0)` are the same key,
+// as are `0.0` and `-0.0`. Whole floats which fit in an isize are stored as integers,
+// other floats by their bits, which is what makes deriving Eq and Hash right.
+// NaN isn't equal to itself, so it can't be a key.
+
+use std::fmt;
+
+use crate::scanner::{LiteralType, Numeric};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Str(String),
+    Integer(isize),
+    // Bits of a float which isn't a whole number fitting in an isize, never NaN.
+    Float(u64),
+    Bool(bool),
+    Nil,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NanKeyError;
+
+impl fmt::Display for NanKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Can't use NaN as a map key.")
+    }
+}
+
+impl MapKey {
+    pub fn new(value: &LiteralType) -> Result<MapKey, NanKeyError> {
+        Ok(match value {
+            LiteralType::Str(s) => MapKey::Str(s.clone()),
+            LiteralType::Num(Numeric::Integer(n)) => MapKey::Integer(*n),
+            LiteralType::Num(Numeric::Float(n)) => Self::float(*n)?,
+            LiteralType::Bool(b) => MapKey::Bool(*b),
+            LiteralType::Null => MapKey::Nil,
+        })
+    }
+
+    fn float(n: f64) -> Result<MapKey, NanKeyError> {
+        if n.is_nan() {
+            return Err(NanKeyError);
+        }
+        // isize::MAX as f64 rounds up to 2^63, which doesn't fit, hence the strict comparison.
+        if n.fract() == 0.0 && n >= isize::MIN as f64 && n < isize::MAX as f64 {
+            return Ok(MapKey::Integer(n as isize));
+        }
+        Ok(MapKey::Float(n.to_bits()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn key(value: LiteralType) -> MapKey {
+        MapKey::new(&value).unwrap()
+    }
+
+    fn num(n: f64) -> MapKey {
+        key(LiteralType::Num(Numeric::Float(n)))
+    }
+
+    fn int(n: isize) -> MapKey {
+        key(LiteralType::Num(Numeric::Integer(n)))
+    }
+
+    #[test]
+    fn test_numbers_are_keyed_by_value() {
+        assert_eq!(num(1.0), int(1));
+        assert_eq!(num(-0.0), num(0.0));
+        assert_eq!(num(-0.0), int(0));
+        assert_ne!(num(1.5), int(1));
+        assert_eq!(num(1.5), num(1.5));
+        assert_eq!(num(1e300), num(1e300));
+        assert_ne!(num(f64::INFINITY), num(f64::NEG_INFINITY));
+        // 2^63 doesn't fit in an isize.
+        let two_63 = 2f64.powi(63);
+        assert_eq!(num(two_63), MapKey::Float(two_63.to_bits()));
+        assert_eq!(num(-two_63), int(isize::MIN));
+
+        let keys: HashSet<MapKey> = [num(1.0), int(1), num(-0.0), int(0), num(0.5)]
+            .into_iter()
+            .collect();
+        assert_eq!(keys.len(), 3);
+    }
+
+    #[test]
+    fn test_other_keys() {
+        let s = |s: &str| key(LiteralType::Str(s.to_string()));
+        assert_eq!(s("1"), s("1"));
+        assert_ne!(s("1"), int(1));
+        assert_ne!(key(LiteralType::Bool(true)), int(1));
+        assert_ne!(key(LiteralType::Null), key(LiteralType::Bool(false)));
+        assert_eq!(key(LiteralType::Null), MapKey::Nil);
+    }
+
+    #[test]
+    fn test_nan_key() {
+        let nan = LiteralType::Num(Numeric::Float(f64::NAN));
+        assert_eq!(MapKey::new(&nan), Err(NanKeyError));
+        assert_eq!(NanKeyError.to_string(), "Can't use NaN as a map key.");
+    }
+}
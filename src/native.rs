@@ -0,0 +1,257 @@
+// Functions implemented in Rust and callable from Lox.
+//
+// Natives check their arguments and return errors as messages without a location,
+// the interpreter reports them at the closing parenthesis of the call, like its own errors.
+// Failures scripts are expected to handle, like a missing file, are error values instead.
+// Natives touching the file system only run when the embedder allows it, see Permissions.
+// What the embedder gives the script, its permissions and arguments, is passed to every call
+// in a Context.
+// Strings are sequences of Unicode scalar values, as in the scanner: lengths and indexes
+// count chars, not bytes. Indexes follow the rules of lists, negative ones count from the end.
+
+mod io;
+mod list;
+mod math;
+mod process;
+mod string;
+
+use std::cell::RefCell;
+
+use crate::scanner::Numeric;
+use crate::value::Value;
+
+pub type NativeResult = Result<Value, String>;
+
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    function: Function,
+    uses_fs: bool,
+}
+
+enum Function {
+    Plain(fn(&[Value]) -> NativeResult),
+    // Reads the context, e.g. the arguments of the script.
+    WithContext(fn(&[Value], &Context) -> NativeResult),
+}
+
+// What scripts may do besides computing, all off by default so untrusted scripts are sandboxed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Permissions {
+    pub fs: bool,
+}
+
+// Set up by the embedder before the script runs, the REPL has no script arguments.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub permissions: Permissions,
+    pub script_args: Vec<String>,
+}
+
+impl Native {
+    const fn new(name: &'static str, arity: usize, function: fn(&[Value]) -> NativeResult) -> Self {
+        Self {
+            name,
+            arity,
+            function: Function::Plain(function),
+            uses_fs: false,
+        }
+    }
+    const fn fs(name: &'static str, arity: usize, function: fn(&[Value]) -> NativeResult) -> Self {
+        Self {
+            uses_fs: true,
+            ..Self::new(name, arity, function)
+        }
+    }
+    const fn with_context(
+        name: &'static str,
+        arity: usize,
+        function: fn(&[Value], &Context) -> NativeResult,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            function: Function::WithContext(function),
+            uses_fs: false,
+        }
+    }
+
+    pub fn call(&self, args: &[Value], context: &Context) -> NativeResult {
+        if self.uses_fs && !context.permissions.fs {
+            return Err(format!(
+                "{}() needs access to files, which isn't allowed.",
+                self.name
+            ));
+        }
+        if args.len() != self.arity {
+            let plural = if self.arity == 1 { "" } else { "s" };
+            return Err(format!(
+                "{}() expects {} argument{plural} but got {}.",
+                self.name,
+                self.arity,
+                args.len()
+            ));
+        }
+        let result = match self.function {
+            Function::Plain(function) => function(args),
+            Function::WithContext(function) => function(args, context),
+        };
+        result.map_err(|message| format!("{}(): {message}", self.name))
+    }
+}
+
+// Every native, they are globals of the interpreter.
+pub fn natives() -> impl Iterator<Item = &'static Native> {
+    string::NATIVES
+        .iter()
+        .chain(list::NATIVES)
+        .chain(math::NATIVES)
+        .chain(io::NATIVES)
+        .chain(process::NATIVES)
+}
+
+// Globals which aren't functions.
+pub fn constants() -> impl Iterator<Item = (&'static str, Value)> {
+    math::CONSTANTS
+        .iter()
+        .map(|&(name, value)| (name, Value::Num(Numeric::Float(value))))
+}
+
+pub fn lookup(name: &str) -> Option<&'static Native> {
+    natives().find(|native| native.name == name)
+}
+
+// Position of `index` in a sequence of `len` items, None when it is out of bounds.
+pub fn resolve_index(index: isize, len: usize) -> Option<usize> {
+    let resolved = if index < 0 {
+        len.checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+    (resolved < len).then_some(resolved)
+}
+
+// Argument checks, `i` is 0-based but messages count from 1 like people do.
+
+fn string(args: &[Value], i: usize) -> Result<&str, String> {
+    match &args[i] {
+        Value::Str(s) => Ok(s),
+        value => Err(type_error(i, "a string", value)),
+    }
+}
+
+fn number(args: &[Value], i: usize) -> Result<&Numeric, String> {
+    match &args[i] {
+        Value::Num(n) => Ok(n),
+        value => Err(type_error(i, "a number", value)),
+    }
+}
+
+// The list itself, changes to it are seen by every variable holding it.
+fn list(args: &[Value], i: usize) -> Result<&RefCell<Vec<Value>>, String> {
+    match &args[i] {
+        Value::List(values) => Ok(values),
+        value => Err(type_error(i, "a list", value)),
+    }
+}
+
+// Whole number, integers and floats without a fractional part are both fine.
+fn whole_number(args: &[Value], i: usize) -> Result<isize, String> {
+    match &args[i] {
+        Value::Num(Numeric::Integer(n)) => Ok(*n),
+        Value::Num(Numeric::Float(n))
+            if n.fract() == 0.0 && *n >= isize::MIN as f64 && *n < isize::MAX as f64 =>
+        {
+            Ok(*n as isize)
+        }
+        value => Err(type_error(i, "a whole number", value)),
+    }
+}
+
+fn type_error(i: usize, expected: &str, value: &Value) -> String {
+    let got = match value {
+        Value::Num(_) => value.to_string(),
+        value => value.type_name().to_string(),
+    };
+    format!("Argument {} must be {expected}, got {got}.", i + 1)
+}
+
+// Lengths and indexes are integers.
+fn integer(n: usize) -> Value {
+    Value::Num(Numeric::Integer(n as isize))
+}
+
+// Calling natives by name in the tests of the submodules.
+#[cfg(test)]
+mod test_support {
+    use super::{lookup, Context, NativeResult};
+    use crate::value::Value;
+
+    pub(super) fn call_result(name: &str, args: &[Value], context: &Context) -> NativeResult {
+        lookup(name).unwrap().call(args, context)
+    }
+
+    // Result of the call as `print` would show it, or the error.
+    pub(super) fn call_with(name: &str, args: &[Value], context: &Context) -> String {
+        match call_result(name, args, context) {
+            Ok(value) => value.to_string(),
+            Err(message) => message,
+        }
+    }
+
+    pub(super) fn call(name: &str, args: &[Value]) -> String {
+        call_with(name, args, &Context::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_index() {
+        assert_eq!(resolve_index(0, 3), Some(0));
+        assert_eq!(resolve_index(2, 3), Some(2));
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(-1, 3), Some(2));
+        assert_eq!(resolve_index(-3, 3), Some(0));
+        assert_eq!(resolve_index(-4, 3), None);
+        assert_eq!(resolve_index(isize::MIN, 3), None);
+        assert_eq!(resolve_index(0, 0), None);
+    }
+
+    #[test]
+    fn test_call_checks_arity() {
+        let upper = lookup("upper").unwrap();
+        assert_eq!(
+            upper.call(&[], &Context::default()).unwrap_err(),
+            "upper() expects 1 argument but got 0."
+        );
+        let replace = lookup("replace").unwrap();
+        assert_eq!(
+            replace
+                .call(&[Value::Nil], &Context::default())
+                .unwrap_err(),
+            "replace() expects 3 arguments but got 1."
+        );
+        assert!(lookup("no_such_native").is_none());
+    }
+
+    #[test]
+    fn test_argument_errors() {
+        let upper = lookup("upper").unwrap();
+        assert_eq!(
+            upper.call(&[Value::Nil], &Context::default()).unwrap_err(),
+            "upper(): Argument 1 must be a string, got nil."
+        );
+        let char_at = lookup("char_at").unwrap();
+        let args = [
+            Value::Str("abc".to_string()),
+            Value::Num(Numeric::Float(1.5)),
+        ];
+        assert_eq!(
+            char_at.call(&args, &Context::default()).unwrap_err(),
+            "char_at(): Argument 2 must be a whole number, got 1.5."
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// Input and output. Files need the `fs` permission, the standard streams don't.
+//
+// I/O failures are error values, a script reading a file which may be missing checks
+// what it got back instead of stopping.
+
+use std::fs;
+use std::io::{self, BufRead};
+
+use super::{string, Native, NativeResult};
+use crate::value::Value;
+
+pub(super) static NATIVES: &[Native] = &[
+    Native::fs("read_file", 1, read_file),
+    Native::fs("write_file", 2, write_file),
+    Native::fs("lines", 1, lines),
+    Native::new("read_line", 0, read_line),
+    Native::new("eprint", 1, eprint),
+];
+
+fn io_error(path: &str, err: io::Error) -> Value {
+    Value::Error(format!("{path}: {err}"))
+}
+
+fn read_file(args: &[Value]) -> NativeResult {
+    let path = string(args, 0)?;
+    Ok(match fs::read_to_string(path) {
+        Ok(content) => Value::Str(content),
+        Err(err) => io_error(path, err),
+    })
+}
+
+// Replaces the file, or creates it.
+fn write_file(args: &[Value]) -> NativeResult {
+    let path = string(args, 0)?;
+    let content = string(args, 1)?;
+    Ok(match fs::write(path, content) {
+        Ok(()) => Value::Nil,
+        Err(err) => io_error(path, err),
+    })
+}
+
+// Lines of the file without their line endings.
+fn lines(args: &[Value]) -> NativeResult {
+    let path = string(args, 0)?;
+    Ok(match fs::read_to_string(path) {
+        Ok(content) => Value::list(content.lines().map(|l| Value::Str(l.to_string())).collect()),
+        Err(err) => io_error(path, err),
+    })
+}
+
+fn read_line(_args: &[Value]) -> NativeResult {
+    Ok(next_line(&mut io::stdin().lock()))
+}
+
+// Next line without its line ending, nil at the end of the input.
+fn next_line(input: &mut impl BufRead) -> Value {
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) => Value::Nil,
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Value::Str(line)
+        }
+        Err(err) => io_error("stdin", err),
+    }
+}
+
+// Like `print`, on the standard error.
+fn eprint(args: &[Value]) -> NativeResult {
+    eprintln!("{}", args[0]);
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::call_with;
+    use super::super::{Context, Permissions};
+    use super::*;
+
+    const ALLOWED: &Context = &Context {
+        permissions: Permissions { fs: true },
+        script_args: Vec::new(),
+    };
+
+    fn s(s: &str) -> Value {
+        Value::Str(s.to_string())
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = std::env::temp_dir().join(format!("tlox-io-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.txt").to_string_lossy().into_owned();
+        assert_eq!(
+            call_with("write_file", &[s(&path), s("a\r\nb\n")], ALLOWED),
+            "nil"
+        );
+        assert_eq!(call_with("read_file", &[s(&path)], ALLOWED), "a\r\nb\n");
+        assert_eq!(call_with("lines", &[s(&path)], ALLOWED), r#"["a", "b"]"#);
+        fs::remove_dir_all(&dir).unwrap();
+        let missing = call_with("read_file", &[s(&path)], ALLOWED);
+        assert!(
+            missing.starts_with(&format!("error: {path}: ")),
+            "{missing}"
+        );
+        assert_eq!(
+            call_with("lines", &[Value::Nil], ALLOWED),
+            "lines(): Argument 1 must be a string, got nil."
+        );
+    }
+
+    #[test]
+    fn test_files_need_permission() {
+        for (name, args) in [
+            ("read_file", vec![s("x")]),
+            ("write_file", vec![s("x"), s("")]),
+            ("lines", vec![s("x")]),
+        ] {
+            assert_eq!(
+                call_with(name, &args, &Context::default()),
+                format!("{name}() needs access to files, which isn't allowed.")
+            );
+        }
+    }
+
+    #[test]
+    fn test_next_line() {
+        let mut input = io::Cursor::new("one\r\ntwo\n\nlast");
+        let read: Vec<String> = (0..5).map(|_| next_line(&mut input).to_string()).collect();
+        assert_eq!(read, ["one", "two", "", "last", "nil"]);
+    }
+}
@@ -0,0 +1,55 @@
+// Functions changing lists in place, `len` is with the string functions.
+
+use super::{list, Native, NativeResult};
+use crate::value::Value;
+
+pub(super) static NATIVES: &[Native] = &[Native::new("push", 2, push), Native::new("pop", 1, pop)];
+
+// `push(xs, v)` appends `v` to `xs`.
+fn push(args: &[Value]) -> NativeResult {
+    list(args, 0)?.borrow_mut().push(args[1].clone());
+    Ok(Value::Nil)
+}
+
+// Removes the last element and returns it.
+fn pop(args: &[Value]) -> NativeResult {
+    list(args, 0)?
+        .borrow_mut()
+        .pop()
+        .ok_or_else(|| "Can't pop from an empty list.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::call;
+    use super::*;
+    use crate::scanner::Numeric;
+
+    fn n(n: isize) -> Value {
+        Value::Num(Numeric::Integer(n))
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let xs = [Value::list(vec![n(1)])];
+        assert_eq!(call("push", &[xs[0].clone(), n(2)]), "nil");
+        // The list is changed in place, not a copy of it.
+        assert_eq!(xs[0].to_string(), "[1, 2]");
+        assert_eq!(call("pop", &xs), "2");
+        assert_eq!(call("pop", &xs), "1");
+        assert_eq!(xs[0].to_string(), "[]");
+        assert_eq!(call("pop", &xs), "pop(): Can't pop from an empty list.");
+    }
+
+    #[test]
+    fn test_push_and_pop_take_lists() {
+        assert_eq!(
+            call("push", &[n(1), n(2)]),
+            "push(): Argument 1 must be a list, got 1."
+        );
+        assert_eq!(
+            call("pop", &[Value::Str("ab".to_string())]),
+            "pop(): Argument 1 must be a list, got string."
+        );
+    }
+}
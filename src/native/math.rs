@@ -0,0 +1,290 @@
+// Math functions and constants.
+//
+// Integers stay integers where the result is one: `abs`, `min`, `max` and the rounding functions
+// give back integers unchanged, `pow` follows the rules of `**`. Rounding a float gives a whole float,
+// `int` converts it to an integer, and `float` goes the other way.
+
+use std::cell::Cell;
+use std::f64::consts;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{number, whole_number, Native, NativeResult};
+use crate::scanner::Numeric;
+use crate::value::Value;
+
+pub(super) static CONSTANTS: &[(&str, f64)] = &[("PI", consts::PI), ("E", consts::E)];
+
+pub(super) static NATIVES: &[Native] = &[
+    Native::new("sqrt", 1, sqrt),
+    Native::new("pow", 2, pow),
+    Native::new("floor", 1, floor),
+    Native::new("ceil", 1, ceil),
+    Native::new("round", 1, round),
+    Native::new("abs", 1, abs),
+    Native::new("min", 2, min),
+    Native::new("max", 2, max),
+    Native::new("sin", 1, sin),
+    Native::new("cos", 1, cos),
+    Native::new("tan", 1, tan),
+    Native::new("log", 1, log),
+    Native::new("exp", 1, exp),
+    Native::new("int", 1, int),
+    Native::new("float", 1, float),
+    Native::new("random", 0, random),
+    Native::new("seed_random", 1, seed_random),
+];
+
+fn float_value(n: f64) -> Value {
+    Value::Num(Numeric::Float(n))
+}
+
+// Applies `f` to the argument as a float.
+fn float_fn(args: &[Value], f: fn(f64) -> f64) -> NativeResult {
+    Ok(float_value(f(number(args, 0)?.as_f64())))
+}
+
+// Integers are already rounded.
+fn rounding(args: &[Value], f: fn(f64) -> f64) -> NativeResult {
+    match number(args, 0)? {
+        Numeric::Integer(n) => Ok(Value::Num(Numeric::Integer(*n))),
+        Numeric::Float(n) => Ok(float_value(f(*n))),
+    }
+}
+
+fn sqrt(args: &[Value]) -> NativeResult {
+    let n = number(args, 0)?.as_f64();
+    if n < 0.0 {
+        return Err(format!("Argument 1 must not be negative, got {n}."));
+    }
+    Ok(float_value(n.sqrt()))
+}
+
+fn pow(args: &[Value]) -> NativeResult {
+    let result = number(args, 0)?.pow(number(args, 1)?);
+    result.map(Value::Num).map_err(|err| err.to_string())
+}
+
+fn floor(args: &[Value]) -> NativeResult {
+    rounding(args, f64::floor)
+}
+
+fn ceil(args: &[Value]) -> NativeResult {
+    rounding(args, f64::ceil)
+}
+
+// Halfway cases round away from zero, `round(-2.5)` is -3.
+fn round(args: &[Value]) -> NativeResult {
+    rounding(args, f64::round)
+}
+
+fn abs(args: &[Value]) -> NativeResult {
+    match number(args, 0)? {
+        Numeric::Integer(n) => n
+            .checked_abs()
+            .map(|n| Value::Num(Numeric::Integer(n)))
+            .ok_or_else(|| "Integer overflow.".to_string()),
+        Numeric::Float(n) => Ok(float_value(n.abs())),
+    }
+}
+
+// The argument which is chosen is given back as it is, integer or float.
+fn min(args: &[Value]) -> NativeResult {
+    let (a, b) = (number(args, 0)?, number(args, 1)?);
+    Ok(Value::Num(
+        if b.as_f64() < a.as_f64() { b } else { a }.clone(),
+    ))
+}
+
+fn max(args: &[Value]) -> NativeResult {
+    let (a, b) = (number(args, 0)?, number(args, 1)?);
+    Ok(Value::Num(
+        if b.as_f64() > a.as_f64() { b } else { a }.clone(),
+    ))
+}
+
+fn sin(args: &[Value]) -> NativeResult {
+    float_fn(args, f64::sin)
+}
+
+fn cos(args: &[Value]) -> NativeResult {
+    float_fn(args, f64::cos)
+}
+
+fn tan(args: &[Value]) -> NativeResult {
+    float_fn(args, f64::tan)
+}
+
+// Natural logarithm.
+fn log(args: &[Value]) -> NativeResult {
+    let n = number(args, 0)?.as_f64();
+    if n <= 0.0 {
+        return Err(format!("Argument 1 must be positive, got {n}."));
+    }
+    Ok(float_value(n.ln()))
+}
+
+fn exp(args: &[Value]) -> NativeResult {
+    float_fn(args, f64::exp)
+}
+
+// Truncates towards zero, `int(-2.7)` is -2.
+fn int(args: &[Value]) -> NativeResult {
+    match number(args, 0)? {
+        Numeric::Integer(n) => Ok(Value::Num(Numeric::Integer(*n))),
+        Numeric::Float(n) => {
+            let n = n.trunc();
+            if n >= isize::MIN as f64 && n < isize::MAX as f64 {
+                Ok(Value::Num(Numeric::Integer(n as isize)))
+            } else {
+                Err(format!("{n} doesn't fit in an integer."))
+            }
+        }
+    }
+}
+
+fn float(args: &[Value]) -> NativeResult {
+    Ok(float_value(number(args, 0)?.as_f64()))
+}
+
+// The generator is SplitMix64, small and good enough for scripts, not for cryptography.
+// Each thread has its own, seeded from the clock until a script calls `seed_random`.
+thread_local! {
+    static RANDOM_STATE: Cell<u64> = Cell::new(clock_seed());
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+fn next_random() -> u64 {
+    RANDOM_STATE.with(|state| {
+        let s = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(s);
+        let mut z = s;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce5_e4b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+// Float in [0, 1), from the 53 high bits so that every value is equally likely.
+fn random(_args: &[Value]) -> NativeResult {
+    Ok(float_value(
+        (next_random() >> 11) as f64 / (1u64 << 53) as f64,
+    ))
+}
+
+// Makes the numbers `random` produces next the same on every run, for tests.
+fn seed_random(args: &[Value]) -> NativeResult {
+    let seed = whole_number(args, 0)?;
+    RANDOM_STATE.with(|state| state.set(seed as u64));
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::call_result;
+    use super::super::{constants, Context};
+    use super::*;
+
+    fn n(n: f64) -> Value {
+        float_value(n)
+    }
+
+    fn i(n: isize) -> Value {
+        Value::Num(Numeric::Integer(n))
+    }
+
+    // Result of the call, with the kind of number, or the error.
+    fn call(name: &str, args: &[Value]) -> String {
+        match call_result(name, args, &Context::default()) {
+            Ok(Value::Num(Numeric::Integer(n))) => format!("integer {n}"),
+            Ok(Value::Num(Numeric::Float(n))) => format!("float {n}"),
+            Ok(value) => value.to_string(),
+            Err(message) => message,
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(call("floor", &[n(-2.5)]), "float -3");
+        assert_eq!(call("ceil", &[n(2.1)]), "float 3");
+        assert_eq!(call("round", &[n(2.5)]), "float 3");
+        assert_eq!(call("round", &[n(-2.5)]), "float -3");
+        assert_eq!(call("floor", &[i(7)]), "integer 7");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(call("int", &[n(-2.7)]), "integer -2");
+        assert_eq!(call("int", &[i(3)]), "integer 3");
+        assert_eq!(
+            call("int", &[n(1e20)]),
+            "int(): 100000000000000000000 doesn't fit in an integer."
+        );
+        assert_eq!(call("float", &[i(3)]), "float 3");
+        assert_eq!(
+            call("int", &[Value::Str("3".to_string())]),
+            "int(): Argument 1 must be a number, got string."
+        );
+    }
+
+    #[test]
+    fn test_integers_stay_integers() {
+        assert_eq!(call("abs", &[i(-3)]), "integer 3");
+        assert_eq!(call("abs", &[n(-3.5)]), "float 3.5");
+        assert_eq!(call("abs", &[i(isize::MIN)]), "abs(): Integer overflow.");
+        assert_eq!(call("min", &[i(2), n(2.5)]), "integer 2");
+        assert_eq!(call("max", &[i(2), n(2.5)]), "float 2.5");
+        assert_eq!(call("pow", &[i(2), i(10)]), "integer 1024");
+        assert_eq!(call("pow", &[i(2), i(-1)]), "float 0.5");
+        assert_eq!(call("pow", &[i(2), i(64)]), "pow(): Integer overflow.");
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(call("sqrt", &[n(9.0)]), "float 3");
+        assert_eq!(
+            call("sqrt", &[n(-1.0)]),
+            "sqrt(): Argument 1 must not be negative, got -1."
+        );
+        assert_eq!(call("log", &[n(1.0)]), "float 0");
+        assert_eq!(
+            call("log", &[i(0)]),
+            "log(): Argument 1 must be positive, got 0."
+        );
+        assert_eq!(call("exp", &[i(0)]), "float 1");
+        assert_eq!(call("sin", &[i(0)]), "float 0");
+        assert_eq!(call("cos", &[i(0)]), "float 1");
+        assert_eq!(call("tan", &[i(0)]), "float 0");
+    }
+
+    #[test]
+    fn test_constants() {
+        let names: Vec<String> = constants()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect();
+        assert_eq!(names, ["PI = 3.141592653589793", "E = 2.718281828459045"]);
+    }
+
+    #[test]
+    fn test_seeded_random_is_reproducible() {
+        let draw =
+            |count: usize| -> Vec<String> { (0..count).map(|_| call("random", &[])).collect() };
+        call("seed_random", &[i(42)]);
+        let first = draw(5);
+        call("seed_random", &[i(42)]);
+        assert_eq!(draw(5), first);
+        call("seed_random", &[i(43)]);
+        assert_ne!(draw(5), first);
+        for _ in 0..1000 {
+            let Ok(Value::Num(Numeric::Float(x))) = call_result("random", &[], &Context::default())
+            else {
+                panic!("random() should produce a float");
+            };
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+}
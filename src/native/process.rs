@@ -0,0 +1,53 @@
+// The process running the script: its arguments and environment.
+
+use std::env;
+
+use super::{string, Context, Native, NativeResult};
+use crate::value::Value;
+
+pub(super) static NATIVES: &[Native] = &[
+    Native::with_context("args", 0, args),
+    Native::new("env", 1, env),
+];
+
+// Arguments after the script's path, a new list on every call so scripts can change it.
+fn args(_args: &[Value], context: &Context) -> NativeResult {
+    let args = context.script_args.iter().cloned().map(Value::Str);
+    Ok(Value::list(args.collect()))
+}
+
+// nil when the variable isn't set, or isn't valid Unicode.
+fn env(args: &[Value]) -> NativeResult {
+    Ok(match env::var(string(args, 0)?) {
+        Ok(value) => Value::Str(value),
+        Err(_) => Value::Nil,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{call, call_with};
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        assert_eq!(call("args", &[]), "[]");
+        let context = Context {
+            script_args: vec!["a b".to_string(), "--c".to_string()],
+            ..Context::default()
+        };
+        assert_eq!(call_with("args", &[], &context), r#"["a b", "--c"]"#);
+    }
+
+    #[test]
+    fn test_env() {
+        let path = env::var("PATH").unwrap();
+        assert_eq!(call("env", &[Value::Str("PATH".to_string())]), path);
+        let unset = Value::Str("TLOX_SURELY_NOT_SET".to_string());
+        assert_eq!(call("env", &[unset]), "nil");
+        assert_eq!(
+            call("env", &[Value::Nil]),
+            "env(): Argument 1 must be a string, got nil."
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// String functions. Indexes and lengths count Unicode scalar values.
+
+use super::{integer, list, resolve_index, string, whole_number, Native, NativeResult};
+use crate::scanner::Numeric;
+use crate::value::Value;
+
+pub(super) static NATIVES: &[Native] = &[
+    Native::new("len", 1, len),
+    Native::new("substr", 3, substr),
+    Native::new("index_of", 2, index_of),
+    Native::new("split", 2, split),
+    Native::new("join", 2, join),
+    Native::new("trim", 1, trim),
+    Native::new("upper", 1, upper),
+    Native::new("lower", 1, lower),
+    Native::new("replace", 3, replace),
+    Native::new("starts_with", 2, starts_with),
+    Native::new("char_at", 2, char_at),
+    Native::new("to_string", 1, to_string),
+    Native::new("parse_number", 1, parse_number),
+];
+
+fn str_value(s: impl Into<String>) -> Value {
+    Value::Str(s.into())
+}
+
+// Lists too, it is the one length function.
+fn len(args: &[Value]) -> NativeResult {
+    match &args[0] {
+        Value::Str(s) => Ok(integer(s.chars().count())),
+        Value::List(values) => Ok(integer(values.borrow().len())),
+        value => Err(format!(
+            "Argument 1 must be a string or a list, got {}.",
+            value.type_name()
+        )),
+    }
+}
+
+// `substr(s, start, length)`, the start can be negative, the substring has to fit in `s`.
+fn substr(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?;
+    let start = whole_number(args, 1)?;
+    let length = whole_number(args, 2)?;
+    let len = s.chars().count();
+    // The start may be the end of the string, for an empty substring.
+    let first = if start == len as isize {
+        Some(len)
+    } else {
+        resolve_index(start, len)
+    };
+    match (first, usize::try_from(length)) {
+        (Some(first), Ok(length)) if length <= len - first => Ok(str_value(
+            s.chars().skip(first).take(length).collect::<String>(),
+        )),
+        _ => Err(format!(
+            "Substring of length {length} at {start} is out of bounds for a string of length {len}."
+        )),
+    }
+}
+
+// Index of the first occurrence, -1 when there is none.
+fn index_of(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?;
+    let needle = string(args, 1)?;
+    Ok(match s.find(needle) {
+        Some(byte) => integer(s[..byte].chars().count()),
+        None => Value::Num(Numeric::Integer(-1)),
+    })
+}
+
+// An empty separator splits the string into its characters.
+fn split(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?;
+    let separator = string(args, 1)?;
+    let parts: Vec<Value> = if separator.is_empty() {
+        s.chars().map(str_value).collect()
+    } else {
+        s.split(separator).map(str_value).collect()
+    };
+    Ok(Value::list(parts))
+}
+
+// Elements which aren't strings are joined the way `print` shows them.
+fn join(args: &[Value]) -> NativeResult {
+    let values = list(args, 0)?.borrow();
+    let separator = string(args, 1)?;
+    let parts: Vec<String> = values.iter().map(Value::to_string).collect();
+    Ok(str_value(parts.join(separator)))
+}
+
+fn trim(args: &[Value]) -> NativeResult {
+    Ok(str_value(string(args, 0)?.trim()))
+}
+
+fn upper(args: &[Value]) -> NativeResult {
+    Ok(str_value(string(args, 0)?.to_uppercase()))
+}
+
+fn lower(args: &[Value]) -> NativeResult {
+    Ok(str_value(string(args, 0)?.to_lowercase()))
+}
+
+// Replaces every occurrence.
+fn replace(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?;
+    let from = string(args, 1)?;
+    let to = string(args, 2)?;
+    if from.is_empty() {
+        return Err("Can't replace an empty string.".to_string());
+    }
+    Ok(str_value(s.replace(from, to)))
+}
+
+fn starts_with(args: &[Value]) -> NativeResult {
+    Ok(Value::Bool(string(args, 0)?.starts_with(string(args, 1)?)))
+}
+
+fn char_at(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?;
+    let index = whole_number(args, 1)?;
+    let len = s.chars().count();
+    match resolve_index(index, len) {
+        Some(i) => Ok(str_value(s.chars().nth(i).unwrap())),
+        None => Err(format!(
+            "Index {index} is out of bounds for a string of length {len}."
+        )),
+    }
+}
+
+fn to_string(args: &[Value]) -> NativeResult {
+    Ok(str_value(args[0].to_string()))
+}
+
+// nil when the string isn't a number, so scripts can check user input.
+// Surrounding whitespace is allowed, infinities and NaN aren't numbers of Lox.
+fn parse_number(args: &[Value]) -> NativeResult {
+    let s = string(args, 0)?.trim();
+    Ok(match s.parse::<f64>() {
+        Ok(n)
+            if n.is_finite()
+                && !s.contains(|c: char| c.is_alphabetic() && c != 'e' && c != 'E') =>
+        {
+            Value::Num(Numeric::Float(n))
+        }
+        _ => Value::Nil,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::call;
+    use super::*;
+
+    fn s(s: &str) -> Value {
+        str_value(s)
+    }
+
+    fn n(n: f64) -> Value {
+        Value::Num(Numeric::Float(n))
+    }
+
+    #[test]
+    fn test_lengths_count_chars() {
+        assert_eq!(call("len", &[s("héllo")]), "5");
+        assert_eq!(call("len", &[s("")]), "0");
+        assert_eq!(call("len", &[Value::list(vec![n(1.0), n(2.0)])]), "2");
+        assert_eq!(
+            call("len", &[n(1.0)]),
+            "len(): Argument 1 must be a string or a list, got number."
+        );
+    }
+
+    #[test]
+    fn test_substr() {
+        assert_eq!(call("substr", &[s("héllo"), n(1.0), n(3.0)]), "éll");
+        assert_eq!(call("substr", &[s("héllo"), n(-2.0), n(2.0)]), "lo");
+        assert_eq!(call("substr", &[s("abc"), n(3.0), n(0.0)]), "");
+        assert_eq!(
+            call("substr", &[s("abc"), n(2.0), n(2.0)]),
+            "substr(): Substring of length 2 at 2 is out of bounds for a string of length 3."
+        );
+        assert_eq!(
+            call("substr", &[s("abc"), n(0.0), n(-1.0)]),
+            "substr(): Substring of length -1 at 0 is out of bounds for a string of length 3."
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        assert_eq!(call("index_of", &[s("héllo"), s("l")]), "2");
+        assert_eq!(call("index_of", &[s("abc"), s("z")]), "-1");
+        assert_eq!(call("starts_with", &[s("héllo"), s("hé")]), "true");
+        assert_eq!(call("starts_with", &[s("abc"), s("b")]), "false");
+        assert_eq!(call("char_at", &[s("héllo"), n(1.0)]), "é");
+        assert_eq!(call("char_at", &[s("héllo"), n(-1.0)]), "o");
+        assert_eq!(
+            call("char_at", &[s("abc"), n(3.0)]),
+            "char_at(): Index 3 is out of bounds for a string of length 3."
+        );
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        assert_eq!(
+            call("split", &[s("a,b,,c"), s(",")]),
+            r#"["a", "b", "", "c"]"#
+        );
+        assert_eq!(call("split", &[s("hé"), s("")]), r#"["h", "é"]"#);
+        let parts = Value::list(vec![s("a"), n(1.0), Value::Nil]);
+        assert_eq!(call("join", &[parts, s(", ")]), "a, 1, nil");
+        assert_eq!(
+            call("join", &[s("ab"), s("")]),
+            "join(): Argument 1 must be a list, got string."
+        );
+    }
+
+    #[test]
+    fn test_transformations() {
+        assert_eq!(call("trim", &[s("  a b \n")]), "a b");
+        assert_eq!(call("upper", &[s("straße")]), "STRASSE");
+        assert_eq!(call("lower", &[s("ÉA")]), "éa");
+        assert_eq!(call("replace", &[s("a-b-c"), s("-"), s("+")]), "a+b+c");
+        assert_eq!(
+            call("replace", &[s("abc"), s(""), s("x")]),
+            "replace(): Can't replace an empty string."
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(call("to_string", &[n(1.5)]), "1.5");
+        assert_eq!(call("to_string", &[Value::Bool(true)]), "true");
+        assert_eq!(call("parse_number", &[s(" 42 ")]), "42");
+        assert_eq!(call("parse_number", &[s("-1.5e3")]), "-1500");
+        assert_eq!(call("parse_number", &[s("2E2")]), "200");
+        for not_a_number in ["", "abc", "1x", "inf", "NaN", "1e999"] {
+            assert_eq!(
+                call("parse_number", &[s(not_a_number)]),
+                "nil",
+                "{not_a_number}"
+            );
+        }
+    }
+}
@@ -0,0 +1,361 @@
+// Arithmetic on Lox numbers, for the interpreter to call once it exists.
+//
+// Integers stay integers when both operands are, and overflowing is an error rather than
+// wrapping. As soon as one operand is a float, the other one is converted and the result
+// is a float. The exceptions:
+// - `/` always produces a float, `7 / 2` is 3.5, `div` is the integer division;
+// - `div` and `%` round towards negative infinity like Python's, so `a div b * b + a % b == a`
+//   and the remainder has the sign of the divisor, `-7 % 3` is 2;
+// - `**` with a negative integer exponent produces a float, `2 ** -1` is 0.5.
+// Dividing by zero is an error for all three division operators, integer or float,
+// and so is raising zero to a negative power.
+//
+// Bitwise operators `& | ^ ~ << >>` are only defined on integers, floats are an error even when
+// they are whole numbers. Shifts discard the bits shifted out, `>>` keeps the sign, and shifting
+// by a negative amount or by the width of an integer or more is an error.
+
+use std::fmt;
+
+use crate::scanner::Numeric;
+
+#[derive(Debug, PartialEq)]
+pub enum ArithmeticError {
+    DivisionByZero,
+    Overflow,
+    NotAnInteger,
+    ShiftOutOfRange,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArithmeticError::DivisionByZero => write!(f, "Division by zero."),
+            ArithmeticError::Overflow => write!(f, "Integer overflow."),
+            ArithmeticError::NotAnInteger => write!(f, "Operands must be integers."),
+            ArithmeticError::ShiftOutOfRange => {
+                write!(f, "Shift amount must be between 0 and {}.", isize::BITS - 1)
+            }
+        }
+    }
+}
+
+type ArithmeticResult = Result<Numeric, ArithmeticError>;
+
+impl Numeric {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Numeric::Integer(n) => n as f64,
+            Numeric::Float(n) => n,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.as_f64() == 0.0
+    }
+
+    pub fn add(&self, other: &Numeric) -> ArithmeticResult {
+        self.combine(other, isize::checked_add, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Numeric) -> ArithmeticResult {
+        self.combine(other, isize::checked_sub, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Numeric) -> ArithmeticResult {
+        self.combine(other, isize::checked_mul, |a, b| a * b)
+    }
+
+    // `/`
+    pub fn div(&self, other: &Numeric) -> ArithmeticResult {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        Ok(Numeric::Float(self.as_f64() / other.as_f64()))
+    }
+
+    // `div`
+    pub fn int_div(&self, other: &Numeric) -> ArithmeticResult {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        self.combine(other, floor_div, |a, b| (a / b).floor())
+    }
+
+    // `%`
+    pub fn rem(&self, other: &Numeric) -> ArithmeticResult {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        self.combine(other, floor_rem, |a, b| {
+            let r = a % b;
+            if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                r + b
+            } else {
+                r
+            }
+        })
+    }
+
+    // `**`
+    pub fn pow(&self, other: &Numeric) -> ArithmeticResult {
+        if self.is_zero() && other.as_f64() < 0.0 {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) if *b >= 0 => {
+                // The powers of these don't grow, whatever the exponent.
+                let power = match a {
+                    0 => isize::from(*b == 0),
+                    1 => 1,
+                    -1 if b % 2 == 0 => 1,
+                    -1 => -1,
+                    _ => u32::try_from(*b)
+                        .ok()
+                        .and_then(|b| a.checked_pow(b))
+                        .ok_or(ArithmeticError::Overflow)?,
+                };
+                Ok(Numeric::Integer(power))
+            }
+            _ => Ok(Numeric::Float(self.as_f64().powf(other.as_f64()))),
+        }
+    }
+
+    // `&`
+    pub fn bit_and(&self, other: &Numeric) -> ArithmeticResult {
+        integers(self, other, |a, b| Ok(a & b))
+    }
+
+    // `|`
+    pub fn bit_or(&self, other: &Numeric) -> ArithmeticResult {
+        integers(self, other, |a, b| Ok(a | b))
+    }
+
+    // `^`
+    pub fn bit_xor(&self, other: &Numeric) -> ArithmeticResult {
+        integers(self, other, |a, b| Ok(a ^ b))
+    }
+
+    // `~`
+    pub fn bit_not(&self) -> ArithmeticResult {
+        match self {
+            Numeric::Integer(n) => Ok(Numeric::Integer(!n)),
+            Numeric::Float(_) => Err(ArithmeticError::NotAnInteger),
+        }
+    }
+
+    // `<<`
+    pub fn shl(&self, other: &Numeric) -> ArithmeticResult {
+        integers(self, other, |a, b| Ok(a << shift_amount(b)?))
+    }
+
+    // `>>`
+    pub fn shr(&self, other: &Numeric) -> ArithmeticResult {
+        integers(self, other, |a, b| Ok(a >> shift_amount(b)?))
+    }
+
+    fn combine(
+        &self,
+        other: &Numeric,
+        integers: fn(isize, isize) -> Option<isize>,
+        floats: fn(f64, f64) -> f64,
+    ) -> ArithmeticResult {
+        match (self, other) {
+            (Numeric::Integer(a), Numeric::Integer(b)) => integers(*a, *b)
+                .map(Numeric::Integer)
+                .ok_or(ArithmeticError::Overflow),
+            _ => Ok(Numeric::Float(floats(self.as_f64(), other.as_f64()))),
+        }
+    }
+}
+
+fn integers(
+    a: &Numeric,
+    b: &Numeric,
+    op: fn(isize, isize) -> Result<isize, ArithmeticError>,
+) -> ArithmeticResult {
+    match (a, b) {
+        (Numeric::Integer(a), Numeric::Integer(b)) => op(*a, *b).map(Numeric::Integer),
+        _ => Err(ArithmeticError::NotAnInteger),
+    }
+}
+
+fn shift_amount(n: isize) -> Result<u32, ArithmeticError> {
+    u32::try_from(n)
+        .ok()
+        .filter(|n| *n < isize::BITS)
+        .ok_or(ArithmeticError::ShiftOutOfRange)
+}
+
+// Division rounding towards negative infinity, None on overflow (isize::MIN div -1).
+fn floor_div(a: isize, b: isize) -> Option<isize> {
+    let q = a.checked_div(b)?;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        Some(q - 1)
+    } else {
+        Some(q)
+    }
+}
+
+fn floor_rem(a: isize, b: isize) -> Option<isize> {
+    let r = a.checked_rem(b)?;
+    if r != 0 && (r < 0) != (b < 0) {
+        Some(r + b)
+    } else {
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Numeric::{Float, Integer};
+
+    // Numeric has no PartialEq, floats are compared by their bits.
+    fn same(result: ArithmeticResult, expected: Numeric) -> bool {
+        match (result, expected) {
+            (Ok(Integer(a)), Integer(b)) => a == b,
+            (Ok(Float(a)), Float(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_integers_stay_integers() {
+        assert!(same(Integer(2).add(&Integer(3)), Integer(5)));
+        assert!(same(Integer(2).sub(&Integer(3)), Integer(-1)));
+        assert!(same(Integer(2).mul(&Integer(3)), Integer(6)));
+        assert!(same(Integer(2).pow(&Integer(10)), Integer(1024)));
+        assert!(same(Integer(7).int_div(&Integer(2)), Integer(3)));
+        assert!(same(Integer(7).rem(&Integer(2)), Integer(1)));
+        // `/` is always a float division.
+        assert!(same(Integer(7).div(&Integer(2)), Float(3.5)));
+    }
+
+    #[test]
+    fn test_floats_are_contagious() {
+        assert!(same(Integer(2).add(&Float(0.5)), Float(2.5)));
+        assert!(same(Float(7.0).int_div(&Integer(2)), Float(3.0)));
+        assert!(same(Float(7.5).rem(&Integer(2)), Float(1.5)));
+        assert!(same(Float(2.0).pow(&Integer(3)), Float(8.0)));
+        assert!(same(Integer(2).pow(&Integer(-1)), Float(0.5)));
+    }
+
+    #[test]
+    fn test_floored_division() {
+        for (a, b, q, r) in [
+            (7, 3, 2, 1),
+            (-7, 3, -3, 2),
+            (7, -3, -3, -2),
+            (-7, -3, 2, -1),
+        ] {
+            assert!(
+                same(Integer(a).int_div(&Integer(b)), Integer(q)),
+                "{a} div {b}"
+            );
+            assert!(same(Integer(a).rem(&Integer(b)), Integer(r)), "{a} % {b}");
+            let (a, b) = (Float(a as f64), Float(b as f64));
+            assert!(same(a.int_div(&b), Float(q as f64)));
+            assert!(same(a.rem(&b), Float(r as f64)));
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        for zero in [Integer(0), Float(0.0), Float(-0.0)] {
+            assert_eq!(
+                Integer(1).div(&zero).unwrap_err(),
+                ArithmeticError::DivisionByZero
+            );
+            assert_eq!(
+                Float(1.0).int_div(&zero).unwrap_err(),
+                ArithmeticError::DivisionByZero
+            );
+            assert_eq!(
+                Integer(1).rem(&zero).unwrap_err(),
+                ArithmeticError::DivisionByZero
+            );
+        }
+        for zero in [Integer(0), Float(0.0)] {
+            for exponent in [Integer(-1), Float(-0.5)] {
+                assert_eq!(
+                    zero.pow(&exponent).unwrap_err(),
+                    ArithmeticError::DivisionByZero
+                );
+            }
+        }
+        assert_eq!(
+            ArithmeticError::DivisionByZero.to_string(),
+            "Division by zero."
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let max = Integer(isize::MAX);
+        assert_eq!(max.add(&Integer(1)).unwrap_err(), ArithmeticError::Overflow);
+        assert_eq!(
+            Integer(isize::MIN).int_div(&Integer(-1)).unwrap_err(),
+            ArithmeticError::Overflow
+        );
+        assert_eq!(
+            Integer(2).pow(&Integer(64)).unwrap_err(),
+            ArithmeticError::Overflow
+        );
+        assert_eq!(
+            Integer(2).pow(&Integer(1 << 40)).unwrap_err(),
+            ArithmeticError::Overflow
+        );
+        // Unless the powers stay the same.
+        let big = Integer(1 << 40);
+        assert!(same(Integer(1).pow(&big), Integer(1)));
+        assert!(same(Integer(0).pow(&big), Integer(0)));
+        assert!(same(Integer(0).pow(&Integer(0)), Integer(1)));
+        assert!(same(Integer(-1).pow(&big), Integer(1)));
+        assert!(same(Integer(-1).pow(&Integer((1 << 40) + 1)), Integer(-1)));
+        assert!(same(Integer(-1).pow(&Integer(-3)), Float(-1.0)));
+        // Floats don't overflow, they become infinite.
+        assert!(same(
+            Float(isize::MAX as f64).mul(&Float(1e300)),
+            Float(f64::INFINITY)
+        ));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert!(same(
+            Integer(0b1100).bit_and(&Integer(0b1010)),
+            Integer(0b1000)
+        ));
+        assert!(same(
+            Integer(0b1100).bit_or(&Integer(0b1010)),
+            Integer(0b1110)
+        ));
+        assert!(same(
+            Integer(0b1100).bit_xor(&Integer(0b1010)),
+            Integer(0b0110)
+        ));
+        assert!(same(Integer(0).bit_not(), Integer(-1)));
+        assert!(same(Integer(1).shl(&Integer(4)), Integer(16)));
+        assert!(same(Integer(-16).shr(&Integer(2)), Integer(-4)));
+        // Bits shifted out are lost.
+        assert!(same(Integer(3).shl(&Integer(63)), Integer(isize::MIN)));
+    }
+
+    #[test]
+    fn test_bitwise_errors() {
+        let not_integer = ArithmeticError::NotAnInteger;
+        assert_eq!(Float(1.0).bit_and(&Integer(1)).unwrap_err(), not_integer);
+        assert_eq!(Integer(1).bit_or(&Float(1.0)).unwrap_err(), not_integer);
+        assert_eq!(Float(2.0).bit_not().unwrap_err(), not_integer);
+        assert_eq!(Integer(1).shl(&Float(1.0)).unwrap_err(), not_integer);
+        assert_eq!(not_integer.to_string(), "Operands must be integers.");
+        let out_of_range = ArithmeticError::ShiftOutOfRange;
+        assert_eq!(Integer(1).shl(&Integer(64)).unwrap_err(), out_of_range);
+        assert_eq!(Integer(1).shr(&Integer(-1)).unwrap_err(), out_of_range);
+        assert_eq!(
+            out_of_range.to_string(),
+            "Shift amount must be between 0 and 63."
+        );
+    }
+}
@@ -1,162 +1,903 @@
-use crate::ast::Expr;
-use crate::scanner::{LiteralType, Token};
+use crate::ast::{Expr, Span};
+use crate::scanner::{ErrorHandler, LiteralType, Numeric, Token};
 use crate::types::TokenType;
-use std::io;
 
-pub struct Parser {
+pub struct Parser<'a> {
     tokens: Vec<Token>,
     current: usize,
+    error_handler: &'a mut ErrorHandler,
+    // Nested parse_precedence calls.
+    nesting: usize,
+    // Depth of the tree being built: the nesting, plus the operators of the loop in
+    // parse_operand, each of which puts the expression so far one level down.
+    depth: usize,
+    // Index of the last operator reported by missing_left_operand.
+    missing_operand_at: Option<usize>,
 }
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+
+// Expressions nested deeper than this, e.g. `((((1))))` or `- - - -1`, are an error rather
+// than a stack overflow here. Every level takes a few recursive calls of the parser.
+const MAX_NESTING: usize = 256;
+
+// Same for the depth of the tree, which chains of operators like `1 + 1 + 1 + 1` add to
+// without recursing in the parser. The passes walking the tree recursively after us,
+// printing and dropping it included, take a single call or two per level.
+const MAX_DEPTH: usize = 2048;
+
+// The error has already been reported when this is returned.
+#[derive(Debug)]
+pub struct ParseError;
+
+type ParseResult = Result<Expr, ParseError>;
+
+// How tightly an operator binds its operands, from the loosest to the tightest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    // Tokens that can't continue an expression.
+    None,
+    // List elements are parsed at the Assignment level so that commas separate them,
+    // function arguments will be too.
+    Comma,
+    Assignment,
+    Conditional,
+    // Below equality like in C, `x & MASK == 0` is `x & (MASK == 0)`.
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equality,
+    Comparison,
+    Shift,
+    Term,
+    Factor,
+    Unary,
+    // Binds tighter than unary minus, `-2 ** 2` is `-(2 ** 2)`.
+    Exponent,
+    Call,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::BitOr,
+            Precedence::BitOr => Precedence::BitXor,
+            Precedence::BitXor => Precedence::BitAnd,
+            Precedence::BitAnd => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Shift,
+            Precedence::Shift => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Exponent,
+            Precedence::Exponent | Precedence::Call => Precedence::Call,
+        }
     }
+}
+
+type PrefixFn<'a> = fn(&mut Parser<'a>) -> ParseResult;
+type InfixFn<'a> = fn(&mut Parser<'a>, Expr) -> ParseResult;
 
-    fn expression(&mut self) -> Expr {
-        self.equality()
+// How to parse an expression starting with a token (prefix),
+// and an expression where the token follows a complete operand (infix).
+struct ParseRule<'a> {
+    prefix: Option<PrefixFn<'a>>,
+    infix: Option<InfixFn<'a>>,
+    precedence: Precedence,
+    associativity: Associativity,
+}
+
+impl<'a> ParseRule<'a> {
+    fn none() -> Self {
+        Self {
+            prefix: None,
+            infix: None,
+            precedence: Precedence::None,
+            associativity: Associativity::Left,
+        }
     }
+    fn prefix(prefix: PrefixFn<'a>) -> Self {
+        Self {
+            prefix: Some(prefix),
+            ..Self::none()
+        }
+    }
+    fn infix(infix: InfixFn<'a>, precedence: Precedence, associativity: Associativity) -> Self {
+        Self {
+            infix: Some(infix),
+            precedence,
+            associativity,
+            ..Self::none()
+        }
+    }
+    // Left-associative binary operator, with an error production for when
+    // it starts an expression.
+    fn binary(precedence: Precedence) -> Self {
+        Self {
+            prefix: Some(Parser::missing_left_operand),
+            ..Self::infix(Parser::binary, precedence, Associativity::Left)
+        }
+    }
+    fn with_prefix(self, prefix: PrefixFn<'a>) -> Self {
+        Self {
+            prefix: Some(prefix),
+            ..self
+        }
+    }
+}
 
-    fn equality(&mut self) -> Expr {
-        let mut expr: Expr = self.comparison();
-        while self.match_token_types(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator: Token = self.previous();
-            let right: Expr = self.comparison();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+// The operator table, adding an operator is adding a line here.
+fn rule<'a>(ttype: TokenType) -> ParseRule<'a> {
+    use Precedence as P;
+    use TokenType as T;
+    match ttype {
+        T::LeftParen => ParseRule::prefix(Parser::grouping),
+        T::LeftBrace => ParseRule::prefix(Parser::map),
+        T::LeftBracket => {
+            ParseRule::infix(Parser::index, P::Call, Associativity::Left).with_prefix(Parser::list)
         }
-        expr
+        T::Comma => ParseRule::binary(P::Comma),
+        T::Equal | T::PlusEqual | T::MinusEqual | T::StarEqual | T::SlashEqual => {
+            ParseRule::infix(Parser::assignment, P::Assignment, Associativity::Right)
+        }
+        T::PlusPlus | T::MinusMinus => {
+            ParseRule::infix(Parser::postfix_increment, P::Call, Associativity::Left)
+                .with_prefix(Parser::prefix_increment)
+        }
+        T::Question => ParseRule::infix(Parser::conditional, P::Conditional, Associativity::Right),
+        T::Minus => ParseRule::binary(P::Term).with_prefix(Parser::unary),
+        T::Plus => ParseRule::binary(P::Term),
+        T::Slash => ParseRule::binary(P::Factor),
+        T::Star => ParseRule::binary(P::Factor),
+        T::Percent => ParseRule::binary(P::Factor),
+        T::Div => ParseRule::binary(P::Factor),
+        T::StarStar => ParseRule {
+            associativity: Associativity::Right,
+            ..ParseRule::binary(P::Exponent)
+        },
+        T::Bang | T::Tilde => ParseRule::prefix(Parser::unary),
+        T::Ampersand => ParseRule::binary(P::BitAnd),
+        T::Caret => ParseRule::binary(P::BitXor),
+        T::Pipe => ParseRule::binary(P::BitOr),
+        T::LessLess | T::GreaterGreater => ParseRule::binary(P::Shift),
+        T::BangEqual => ParseRule::binary(P::Equality),
+        T::EqualEqual => ParseRule::binary(P::Equality),
+        T::Greater => ParseRule::binary(P::Comparison),
+        T::GreaterEqual => ParseRule::binary(P::Comparison),
+        T::Less => ParseRule::binary(P::Comparison),
+        T::LessEqual => ParseRule::binary(P::Comparison),
+        T::Identifier => ParseRule::prefix(Parser::variable),
+        T::String | T::Number => ParseRule::prefix(Parser::literal_token),
+        T::False | T::True | T::Nil => ParseRule::prefix(Parser::keyword_literal),
+        T::Break | T::Continue => ParseRule::prefix(Parser::loop_control),
+        _ => ParseRule::none(),
     }
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
-        while self.match_token_types(vec![
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator: Token = self.previous();
-            let right: Expr = self.term();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<Token>, error_handler: &'a mut ErrorHandler) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            error_handler,
+            nesting: 0,
+            depth: 0,
+            missing_operand_at: None,
         }
-        expr
     }
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-        while self.match_token_types(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator: Token = self.previous();
-            let right: Expr = self.factor();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+
+    fn expression(&mut self) -> ParseResult {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    // Parses an expression whose operators bind at least as tightly as `precedence`.
+    fn parse_precedence(&mut self, precedence: Precedence) -> ParseResult {
+        if self.nesting == MAX_NESTING || self.depth == MAX_DEPTH {
+            return Err(self.error_at(self.current, "Expression is nested too deeply."));
         }
+        self.nesting += 1;
+        self.depth += 1;
+        let expr = self.parse_operand(precedence);
+        self.nesting -= 1;
+        self.depth -= 1;
         expr
     }
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
-        while self.match_token_types(vec![TokenType::Slash, TokenType::Star]) {
-            let operator: Token = self.previous();
-            let right: Expr = self.unary();
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
+
+    fn parse_operand(&mut self, precedence: Precedence) -> ParseResult {
+        let Some(prefix) = rule(self.peek().ttype).prefix else {
+            return Err(self.error_at(self.current, "Expect expression."));
+        };
+        self.advance();
+        let mut expr = prefix(self)?;
+        let depth = self.depth;
+        let result = loop {
+            let rule = rule(self.peek().ttype);
+            let Some(infix) = rule.infix else {
+                break Ok(expr);
             };
-        }
-        expr
+            if rule.precedence < precedence {
+                break Ok(expr);
+            }
+            // Counting the operand after the operator, reported at the operator.
+            if self.depth + 1 == MAX_DEPTH {
+                break Err(self.error_at(self.current, "Expression is nested too deeply."));
+            }
+            self.depth += 1;
+            self.advance();
+            expr = match infix(self, expr) {
+                Ok(expr) => expr,
+                Err(err) => break Err(err),
+            };
+        };
+        self.depth = depth;
+        result
     }
-    fn unary(&mut self) -> Expr {
-        if self.match_token_types(vec![TokenType::Bang, TokenType::Minus]) {
-            let operator: Token = self.previous();
-            let right: Expr = self.unary();
-            return Expr::Unary {
+
+    // Infix rules are called with the operator just consumed.
+    fn binary(&mut self, left: Expr) -> ParseResult {
+        let operator = self.previous().clone();
+        let rule = rule(operator.ttype);
+        let right = match rule.associativity {
+            Associativity::Left => self.parse_precedence(rule.precedence.next())?,
+            Associativity::Right => self.parse_precedence(rule.precedence)?,
+        };
+        Ok(Expr::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    fn conditional(&mut self, condition: Expr) -> ParseResult {
+        // Like in C, the middle operand can be any expression, it is delimited by ':'.
+        let then_branch = self.expression()?;
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional expression.",
+        )?;
+        let else_branch = self.parse_precedence(Precedence::Conditional)?;
+        Ok(Expr::Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    // `=` and the compound assignment operators.
+    fn assignment(&mut self, target: Expr) -> ParseResult {
+        let operator_index = self.current - 1;
+        let operator = self.previous().clone();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        self.assign(target, operator, value, operator_index)
+    }
+
+    // `xs[0]++`, the operator is kept to tell it apart from `++xs[0]`.
+    fn postfix_increment(&mut self, target: Expr) -> ParseResult {
+        let operator = self.previous().clone();
+        let one = self.literal(LiteralType::Num(Numeric::Integer(1)));
+        self.assign(target, operator, one, self.current - 1)
+    }
+
+    // `++xs[0]` is `xs[0] += 1`, and `--xs[0]` is `xs[0] -= 1`.
+    fn prefix_increment(&mut self) -> ParseResult {
+        let operator_index = self.current - 1;
+        let operator = self.previous();
+        let (ttype, lexeme) = match operator.ttype {
+            TokenType::PlusPlus => (TokenType::PlusEqual, "+="),
+            _ => (TokenType::MinusEqual, "-="),
+        };
+        let compound = Token::new(
+            ttype,
+            lexeme.to_string(),
+            LiteralType::Null,
+            operator.line,
+            operator.col,
+        );
+        let one = self.literal(LiteralType::Num(Numeric::Integer(1)));
+        let target = self.parse_precedence(Precedence::Unary)?;
+        self.assign(target, compound, one, operator_index)
+    }
+
+    // Only elements can be assigned to for now, errors are reported at the token
+    // with index `error_index`.
+    fn assign(
+        &mut self,
+        target: Expr,
+        operator: Token,
+        value: Expr,
+        error_index: usize,
+    ) -> ParseResult {
+        match target {
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => Ok(Expr::IndexSet {
+                object,
+                bracket,
+                index,
                 operator,
-                right: Box::new(right),
-            };
+                value: Box::new(value),
+            }),
+            // Like in the book, no need to synchronize, the parser isn't confused.
+            _ => {
+                self.error_at(error_index, "Invalid assignment target.");
+                Ok(target)
+            }
         }
-        self.primary()
     }
 
-    fn primary(&mut self) -> Expr {
-        //TODO: how do we get the line at this point?
-        if self.match_token_type(TokenType::False) {
-            return Expr::Literal(LiteralType::Bool(false));
-        }
-        if self.match_token_type(TokenType::True) {
-            return Expr::Literal(LiteralType::Bool(false));
+    fn index(&mut self, object: Expr) -> ParseResult {
+        let index = self.expression()?;
+        let bracket = self
+            .consume(TokenType::RightBracket, "Expect ']' after index.")?
+            .clone();
+        Ok(Expr::Index {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+        })
+    }
+
+    // Error production for a binary operator at the start of an expression, e.g. `* 3`.
+    fn missing_left_operand(&mut self) -> ParseResult {
+        let operator = self.previous();
+        let message = format!(
+            "Binary operator '{}' needs a left operand.",
+            operator.lexeme
+        );
+        let precedence = rule(operator.ttype).precedence.next();
+        // In `* * 3` the second operator misses its left operand because of the first,
+        // one error is enough for the lot.
+        let index = self.current - 1;
+        if self.missing_operand_at.is_none_or(|at| at + 1 != index) {
+            self.error_at(index, &message);
         }
-        if self.match_token_type(TokenType::Nil) {
-            return Expr::Literal(LiteralType::Null);
+        self.missing_operand_at = Some(index);
+        // Parse and discard the right operand, so that we carry on after it
+        // and report the errors that follow.
+        self.parse_precedence(precedence)
+    }
+
+    // Prefix rules are called with their first token just consumed.
+    fn unary(&mut self) -> ParseResult {
+        let operator = self.previous().clone();
+        let right: Expr = self.parse_precedence(Precedence::Unary)?;
+        Ok(Expr::Unary {
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    fn grouping(&mut self) -> ParseResult {
+        let start = Span::from_token(self.previous());
+        let expr = self.expression()?;
+        let right_paren = self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+        Ok(Expr::Grouping {
+            expression: Box::new(expr),
+            span: start.to(Span::from_token(right_paren)),
+        })
+    }
+
+    fn list(&mut self) -> ParseResult {
+        let start = Span::from_token(self.previous());
+        let mut elements = Vec::new();
+        if self.peek().ttype != TokenType::RightBracket {
+            loop {
+                elements.push(self.parse_precedence(Precedence::Assignment)?);
+                if self.peek().ttype != TokenType::Comma {
+                    break;
+                }
+                self.advance();
+            }
         }
-        if self.match_token_types(vec![TokenType::Number, TokenType::String]) {
-            return Expr::Literal(self.previous().literal);
+        let right_bracket =
+            self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expr::List {
+            elements,
+            span: start.to(Span::from_token(right_bracket)),
+        })
+    }
+
+    // There are no statements yet, so a brace starting an expression is always a map.
+    // Once there are, a statement starting with a brace is a block, like in JavaScript,
+    // and a map in that position has to be put in parentheses.
+    fn map(&mut self) -> ParseResult {
+        let start = Span::from_token(self.previous());
+        let mut entries = Vec::new();
+        if self.peek().ttype != TokenType::RightBrace {
+            loop {
+                entries.push(self.parse_precedence(Precedence::Assignment)?);
+                self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                entries.push(self.parse_precedence(Precedence::Assignment)?);
+                if self.peek().ttype != TokenType::Comma {
+                    break;
+                }
+                self.advance();
+            }
         }
-        if self.match_token_type(TokenType::LeftParen) {
-            let expr = self.expression();
-            self.consume(
-                TokenType::RightParen,
-                "Expect ')' after expression.".to_string(),
-            );
-            return Expr::Grouping {
-                expression: Box::new(expr),
-            };
+        let right_brace = self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::Map {
+            entries,
+            span: start.to(Span::from_token(right_brace)),
+        })
+    }
+
+    fn variable(&mut self) -> ParseResult {
+        Ok(Expr::Variable {
+            name: self.previous().clone(),
+        })
+    }
+
+    fn literal_token(&mut self) -> ParseResult {
+        Ok(self.literal(self.previous().literal.clone()))
+    }
+
+    fn keyword_literal(&mut self) -> ParseResult {
+        let value = match self.previous().ttype {
+            TokenType::False => LiteralType::Bool(false),
+            TokenType::True => LiteralType::Bool(true),
+            _ => LiteralType::Null,
+        };
+        Ok(self.literal(value))
+    }
+
+    // There are no loops yet, so `break` and `continue` are always out of place.
+    fn loop_control(&mut self) -> ParseResult {
+        let message = format!("Can't use '{}' outside of a loop.", self.previous().lexeme);
+        Err(self.error_at(self.current - 1, &message))
+    }
+
+    // Literal coming from the token we've just matched.
+    fn literal(&self, value: LiteralType) -> Expr {
+        Expr::Literal {
+            value,
+            span: Span::from_token(self.previous()),
         }
-        panic!("We should not be here, all the tokens should have been exhausted.");
     }
-    fn consume(&mut self, token_type: TokenType, message: String) -> Token {
+    // Tokens are borrowed, and cloned only to go into the tree.
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, ParseError> {
         if !self.is_at_end() && self.peek().ttype == token_type {
-            return self.advance();
+            self.advance();
+            return Ok(self.previous());
         }
-        //TODO: get rid of this ugliness and use Result instead.
-        panic!("{} {}", self.peek().to_string(), message);
+        Err(self.error_at(self.current, message))
     }
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    // Reports the error at the token with the given index, `self.current` for the next one.
+    fn error_at(&mut self, index: usize, message: &str) -> ParseError {
+        self.error_handler.token_error(&self.tokens[index], message);
+        ParseError
     }
-    fn advance(&mut self) -> Token {
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+    fn advance(&mut self) {
         if !self.is_at_end() {
             self.current += 1;
         }
-        return self.previous();
     }
     fn is_at_end(&self) -> bool {
-        return self.peek().ttype == TokenType::Eof;
+        self.peek().ttype == TokenType::Eof
     }
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
     }
 
-    fn match_token_types(&mut self, token_types: Vec<TokenType>) -> bool {
-        for token_type in token_types {
-            if self.match_token_type(token_type) {
-                return true;
-            }
+    // Errors are reported to the error handler as they are found.
+    pub fn parse(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(self.error_at(self.current, "Expect end of expression."));
         }
-        false
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Expr, ExprVisitor};
+    use crate::scanner::{ErrorHandler, LiteralType, Numeric, Token};
+
+    fn parse(source: &str) -> String {
+        crate::parse(source, &mut ErrorHandler::new())
+            .unwrap()
+            .to_string()
     }
-    fn match_token_type(&mut self, token_type: TokenType) -> bool {
-        if self.is_at_end() {
-            return false;
+
+    // Errors reported while parsing the source.
+    fn errors(source: &str) -> Vec<String> {
+        let mut err_handler = ErrorHandler::new();
+        assert!(crate::parse(source, &mut err_handler).is_none());
+        err_handler.errors
+    }
+
+    // Trees produced by the recursive descent parser this one replaced.
+    #[test]
+    fn test_same_trees_as_recursive_descent() {
+        let cases = [
+            ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+            ("1 * 2 + 3", "(+ (* 1 2) 3)"),
+            ("1 - 2 - 3", "(- (- 1 2) 3)"),
+            ("8 / 4 / 2", "(/ (/ 8 4) 2)"),
+            ("-1 - -2", "(- (- 1) (- 2))"),
+            ("!!false", "(! (! false))"),
+            ("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))"),
+            ("1 == 2 != 3", "(!= (== 1 2) 3)"),
+            ("1 > 2 < 3", "(< (> 1 2) 3)"),
+            (
+                "(1 + 2) * (3 - 4) / -5",
+                "(/ (* (group (+ 1 2)) (group (- 3 4))) (- 5))",
+            ),
+            ("\"a\" + \"b\" <= nil", "(<= (+ a b) )"),
+            (
+                "- (1 + 2) * 3 - 4 / 5 + 6 == 7 != false",
+                "(!= (== (+ (- (* (- (group (+ 1 2))) 3) (/ 4 5)) 6) 7) false)",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
         }
-        match self.peek().ttype == token_type {
-            true => {
-                self.advance();
-                true
+    }
+
+    #[test]
+    fn test_true_literal() {
+        // The old parser turned `true` into false.
+        assert_eq!(parse("!true"), "(! true)");
+    }
+
+    #[test]
+    fn test_conditional() {
+        assert_eq!(parse("1 ? 2 : 3"), "(?: 1 2 3)");
+        // Right associative.
+        assert_eq!(parse("1 ? 2 : 3 ? 4 : 5"), "(?: 1 2 (?: 3 4 5))");
+        assert_eq!(parse("1 ? 2 ? 3 : 4 : 5"), "(?: 1 (?: 2 3 4) 5)");
+        // Binds looser than equality.
+        assert_eq!(parse("1 == 2 ? 3 : 4 + 5"), "(?: (== 1 2) 3 (+ 4 5))");
+    }
+
+    #[test]
+    fn test_conditional_missing_colon() {
+        assert_eq!(
+            errors("1 ? 2 3"),
+            ["[line 1] Error at '3': Expect ':' after then branch of conditional expression."]
+        );
+        assert_eq!(
+            errors("1 ? 2"),
+            ["[line 1] Error at end: Expect ':' after then branch of conditional expression."]
+        );
+    }
+
+    #[test]
+    fn test_comma() {
+        assert_eq!(parse("1, 2, 3"), "(, (, 1 2) 3)");
+        // Binds looser than the conditional operator.
+        assert_eq!(parse("1 ? 2 : 3, 4"), "(, (?: 1 2 3) 4)");
+        assert_eq!(parse("1 ? 2, 3 : 4"), "(?: 1 (, 2 3) 4)");
+        assert_eq!(parse("(1, 2) + 3"), "(+ (group (, 1 2)) 3)");
+    }
+
+    #[test]
+    fn test_missing_expression() {
+        assert_eq!(errors("1 +"), ["[line 1] Error at end: Expect expression."]);
+        assert_eq!(
+            errors("(1"),
+            ["[line 1] Error at end: Expect ')' after expression."]
+        );
+    }
+
+    #[test]
+    fn test_missing_left_operand() {
+        assert_eq!(
+            errors("* 3"),
+            ["[line 1] Error at '*': Binary operator '*' needs a left operand."]
+        );
+        assert_eq!(
+            errors("== 2"),
+            ["[line 1] Error at '==': Binary operator '==' needs a left operand."]
+        );
+        // The right operand is parsed, errors after it are reported too.
+        assert_eq!(
+            errors("(+ 1 * 2) > (<= 3"),
+            [
+                "[line 1] Error at '+': Binary operator '+' needs a left operand.",
+                "[line 1] Error at '<=': Binary operator '<=' needs a left operand.",
+                "[line 1] Error at end: Expect ')' after expression.",
+            ]
+        );
+        // Operators in a row are reported once.
+        assert_eq!(
+            errors("* / 3"),
+            ["[line 1] Error at '*': Binary operator '*' needs a left operand."]
+        );
+        assert_eq!(
+            errors(&format!("{}1", "* ".repeat(100_000))),
+            [
+                "[line 1] Error at '*': Binary operator '*' needs a left operand.",
+                "[line 1] Error at '*': Expression is nested too deeply.",
+            ]
+        );
+        // Minus is a unary operator as well.
+        assert_eq!(parse("- 3"), "(- 3)");
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop() {
+        assert_eq!(
+            errors("1 + break"),
+            ["[line 1] Error at 'break': Can't use 'break' outside of a loop."]
+        );
+        assert_eq!(
+            errors("continue"),
+            ["[line 1] Error at 'continue': Can't use 'continue' outside of a loop."]
+        );
+    }
+
+    // Reserved for exceptions, they can't be variable names.
+    #[test]
+    fn test_exception_keywords() {
+        for keyword in ["throw", "try", "catch", "finally"] {
+            assert_eq!(
+                errors(&format!("xs[{keyword}]")),
+                [format!("[line 1] Error at '{keyword}': Expect expression.")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        // The outermost expression is a level too.
+        assert!(parse(&nested(super::MAX_NESTING - 1)).starts_with("(group"));
+        assert_eq!(
+            errors(&nested(super::MAX_NESTING)),
+            ["[line 1] Error at '1': Expression is nested too deeply."]
+        );
+        assert_eq!(
+            errors(&nested(100_000)),
+            ["[line 1] Error at '(': Expression is nested too deeply."]
+        );
+        assert_eq!(
+            errors(&format!("{}1", "!".repeat(100_000))),
+            ["[line 1] Error at '!': Expression is nested too deeply."]
+        );
+        assert_eq!(
+            errors(&format!("{}1", "[".repeat(100_000))),
+            ["[line 1] Error at '[': Expression is nested too deeply."]
+        );
+    }
+
+    #[test]
+    fn test_trailing_tokens() {
+        assert_eq!(
+            errors("1 2"),
+            ["[line 1] Error at '2': Expect end of expression."]
+        );
+        assert_eq!(
+            errors("1 )"),
+            ["[line 1] Error at ')': Expect end of expression."]
+        );
+    }
+
+    // Operators in a row make the tree deeper without nesting, they have a limit of their own.
+    #[test]
+    fn test_operator_chain_limit() {
+        let chain = |terms: usize| vec!["1"; terms].join(" + ");
+        assert!(parse(&chain(300)).starts_with("(+ (+ "));
+        // Too deep to be printed on the stack of a test thread.
+        let expr = crate::parse(&chain(super::MAX_DEPTH - 1), &mut ErrorHandler::new()).unwrap();
+        assert!(matches!(expr, Expr::Binary { .. }));
+        drop(expr);
+        assert_eq!(
+            errors(&chain(super::MAX_DEPTH)),
+            ["[line 1] Error at '+': Expression is nested too deeply."]
+        );
+        assert_eq!(
+            errors(&chain(100_000)),
+            ["[line 1] Error at '+': Expression is nested too deeply."]
+        );
+        assert_eq!(
+            errors(&format!("xs{}", "[0]".repeat(100_000))),
+            ["[line 1] Error at '[': Expression is nested too deeply."]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_operators() {
+        let cases = [
+            ("7 % 3 * 2", "(* (% 7 3) 2)"),
+            ("1 + 7 div 2", "(+ 1 (div 7 2))"),
+            ("2 ** 3 ** 2", "(** 2 (** 3 2))"),
+            ("-2 ** 2", "(- (** 2 2))"),
+            ("2 ** -1", "(** 2 (- 1))"),
+            ("2 * 3 ** 2", "(* 2 (** 3 2))"),
+            ("xs[0] ** 2", "(** (index xs 0) 2)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+        assert_eq!(
+            errors("div 2"),
+            ["[line 1] Error at 'div': Binary operator 'div' needs a left operand."]
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let cases = [
+            ("a | b ^ c & d", "(| a (^ b (& c d)))"),
+            ("a & b | c", "(| (& a b) c)"),
+            ("x & 1 == 0", "(& x (== 1 0))"),
+            ("1 << 2 + 3", "(<< 1 (+ 2 3))"),
+            ("a << 1 < b >> 1", "(< (<< a 1) (>> b 1))"),
+            ("~x & ~-1", "(& (~ x) (~ (- 1)))"),
+            ("a | b ? c : d", "(?: (| a b) c d)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+        assert_eq!(
+            errors("& 1"),
+            ["[line 1] Error at '&': Binary operator '&' needs a left operand."]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let cases = [
+            ("[]", "(list)"),
+            ("[1, 2 + 3, [4]]", "(list 1 (+ 2 3) (list 4))"),
+            ("[a ? b : c, (d, e)]", "(list (?: a b c) (group (, d e)))"),
+            ("xs[0]", "(index xs 0)"),
+            ("xs[i][j]", "(index (index xs i) j)"),
+            ("-xs[0]", "(- (index xs 0))"),
+            ("[1, 2][0]", "(index (list 1 2) 0)"),
+            ("xs[i, j]", "(index xs (, i j))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let cases = [
+            ("xs[0] = 1", "(= (index xs 0) 1)"),
+            ("xs[0] = ys[1] = 2", "(= (index xs 0) (= (index ys 1) 2))"),
+            ("xs[0] = a ? b : c", "(= (index xs 0) (?: a b c))"),
+            ("xs[0] = 1, 2", "(, (= (index xs 0) 1) 2)"),
+            ("[xs[0] = 1]", "(list (= (index xs 0) 1))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_maps() {
+        let cases = [
+            ("{}", "(map)"),
+            ("{\"a\": 1, \"b\": [2]}", "(map a 1 b (list 2))"),
+            ("{k ? 1 : 2: v}", "(map (?: k 1 2) v)"),
+            ("{\"a\": {}}[\"a\"]", "(index (map a (map)) a)"),
+            ("m[\"a\"] = 1", "(= (index m a) 1)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+        assert_eq!(
+            errors("{\"a\" 1}"),
+            ["[line 1] Error at '1': Expect ':' after map key."]
+        );
+        assert_eq!(
+            errors("{\"a\": 1"),
+            ["[line 1] Error at end: Expect '}' after map entries."]
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let cases = [
+            ("xs[0] += 1", "(+= (index xs 0) 1)"),
+            (
+                "xs[0] -= ys[0] *= 2",
+                "(-= (index xs 0) (*= (index ys 0) 2))",
+            ),
+            ("xs[0] /= 2, 3", "(, (/= (index xs 0) 2) 3)"),
+            ("++xs[0]", "(+= (index xs 0) 1)"),
+            ("--xs[i][j]", "(-= (index (index xs i) j) 1)"),
+            ("xs[0]++", "(++ (index xs 0) 1)"),
+            ("-xs[0]--", "(- (-- (index xs 0) 1))"),
+            ("xs[0]++ + 1", "(+ (++ (index xs 0) 1) 1)"),
+            ("1 - -2", "(- 1 (- 2))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), expected, "{source}");
+        }
+        assert_eq!(
+            errors("a += 1"),
+            ["[line 1] Error at '+=': Invalid assignment target."]
+        );
+        assert_eq!(
+            errors("++a"),
+            ["[line 1] Error at '++': Invalid assignment target."]
+        );
+        assert_eq!(
+            errors("(xs[0])--"),
+            ["[line 1] Error at '--': Invalid assignment target."]
+        );
+    }
+
+    // The target is in the tree once, and the walk visits the list, the index, then the value.
+    #[test]
+    fn test_compound_assignment_walk_order() {
+        struct Names(Vec<String>);
+        impl ExprVisitor for Names {
+            fn visit_variable(&mut self, name: &Token) {
+                self.0.push(name.lexeme.clone());
             }
-            false => false,
+        }
+        let cases = [
+            ("xs[i] += v", vec!["xs", "i", "v"]),
+            ("xs[ys[i]++] *= v", vec!["xs", "ys", "i", "v"]),
+            ("--xs[i]", vec!["xs", "i"]),
+        ];
+        for (source, expected) in cases {
+            let expr = crate::parse(source, &mut ErrorHandler::new()).unwrap();
+            let mut names = Names(Vec::new());
+            names.visit_expr(&expr);
+            assert_eq!(names.0, expected, "{source}");
         }
     }
-    fn parse(&self) -> Result<Expr, io::Error> {
-        //TODO this should not be io::Error, define our own error.
-        let expr = self.expression()?;
-        Ok(expr)
+
+    // Incrementing an integer element keeps it an integer.
+    #[test]
+    fn test_increment_by_integer() {
+        for source in ["xs[0]++", "--xs[0]"] {
+            let expr = crate::parse(source, &mut ErrorHandler::new()).unwrap();
+            let Expr::IndexSet { value, .. } = expr else {
+                panic!("{source} should be an IndexSet");
+            };
+            assert!(
+                matches!(
+                    *value,
+                    Expr::Literal {
+                        value: LiteralType::Num(Numeric::Integer(1)),
+                        ..
+                    }
+                ),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_list_errors() {
+        assert_eq!(
+            errors("[1, 2"),
+            ["[line 1] Error at end: Expect ']' after list elements."]
+        );
+        assert_eq!(
+            errors("[1, ]"),
+            ["[line 1] Error at ']': Expect expression."]
+        );
+        assert_eq!(
+            errors("xs[0"),
+            ["[line 1] Error at end: Expect ']' after index."]
+        );
+        assert_eq!(
+            errors("1 + xs[0] = 2"),
+            ["[line 1] Error at '=': Invalid assignment target."]
+        );
+        assert_eq!(
+            errors("a = 1"),
+            ["[line 1] Error at '=': Invalid assignment target."]
+        );
     }
 }
@@ -20,6 +20,7 @@ pub struct Token {
     pub lexeme: String,
     pub literal: LiteralType,
     pub line: usize,
+    pub col: usize,
 }
 pub struct Scanner<'a> {
     // I use to have source: &str here before.
@@ -30,11 +31,19 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
+    // Index of the first character of the current line, used to compute columns.
+    line_start: usize,
+    // Position of the token being scanned. Strings can span several lines,
+    // so we remember where the token started rather than where it ended.
+    start_line: usize,
+    start_col: usize,
     error_handler: &'a mut ErrorHandler,
 }
 #[derive(Default)]
 pub struct ErrorHandler {
     pub has_error: bool,
+    // Everything reported since the last reset, as printed.
+    pub errors: Vec<String>,
 }
 
 impl fmt::Display for LiteralType {
@@ -50,12 +59,19 @@ impl fmt::Display for LiteralType {
 }
 
 impl Token {
-    pub fn new(ttype: TokenType, lexeme: String, literal: LiteralType, line: usize) -> Token {
+    pub fn new(
+        ttype: TokenType,
+        lexeme: String,
+        literal: LiteralType,
+        line: usize,
+        col: usize,
+    ) -> Token {
         Token {
             ttype,
             lexeme,
             literal,
             line,
+            col,
         }
     }
 }
@@ -75,6 +91,9 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_col: 1,
             error_handler,
         }
     }
@@ -88,13 +107,23 @@ impl<'a> Scanner<'a> {
     }
     fn add_token_wo_literal(&mut self, ttype: TokenType) {
         let text: String = self.source[self.start..self.current].iter().collect();
-        self.tokens
-            .push(Token::new(ttype, text, LiteralType::Null, self.line));
+        self.tokens.push(Token::new(
+            ttype,
+            text,
+            LiteralType::Null,
+            self.start_line,
+            self.start_col,
+        ));
     }
     fn add_token(&mut self, ttype: TokenType, literal: LiteralType) {
         let text: String = self.source[self.start..self.current].iter().collect();
-        self.tokens
-            .push(Token::new(ttype, text, literal, self.line));
+        self.tokens.push(Token::new(
+            ttype,
+            text,
+            literal,
+            self.start_line,
+            self.start_col,
+        ));
     }
     fn scan_token(&mut self) {
         let c: char = self.advance();
@@ -103,12 +132,48 @@ impl<'a> Scanner<'a> {
             ')' => self.add_token_wo_literal(TokenType::RightParen),
             '{' => self.add_token_wo_literal(TokenType::LeftBrace),
             '}' => self.add_token_wo_literal(TokenType::RightBrace),
+            '[' => self.add_token_wo_literal(TokenType::LeftBracket),
+            ']' => self.add_token_wo_literal(TokenType::RightBracket),
             ',' => self.add_token_wo_literal(TokenType::Comma),
             '.' => self.add_token_wo_literal(TokenType::Dot),
-            '-' => self.add_token_wo_literal(TokenType::Minus),
-            '+' => self.add_token_wo_literal(TokenType::Plus),
+            '?' => self.add_token_wo_literal(TokenType::Question),
+            ':' => self.add_token_wo_literal(TokenType::Colon),
+            '-' => {
+                let ttype = if self.cond_match('=') {
+                    TokenType::MinusEqual
+                } else if self.cond_match('-') {
+                    TokenType::MinusMinus
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token_wo_literal(ttype);
+            }
+            '+' => {
+                let ttype = if self.cond_match('=') {
+                    TokenType::PlusEqual
+                } else if self.cond_match('+') {
+                    TokenType::PlusPlus
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token_wo_literal(ttype);
+            }
             ';' => self.add_token_wo_literal(TokenType::Semicolon),
-            '*' => self.add_token_wo_literal(TokenType::Star),
+            '*' => {
+                let ttype = if self.cond_match('=') {
+                    TokenType::StarEqual
+                } else if self.cond_match('*') {
+                    TokenType::StarStar
+                } else {
+                    TokenType::Star
+                };
+                self.add_token_wo_literal(ttype);
+            }
+            '%' => self.add_token_wo_literal(TokenType::Percent),
+            '&' => self.add_token_wo_literal(TokenType::Ampersand),
+            '|' => self.add_token_wo_literal(TokenType::Pipe),
+            '^' => self.add_token_wo_literal(TokenType::Caret),
+            '~' => self.add_token_wo_literal(TokenType::Tilde),
             '!' => {
                 let matched_equal: bool = self.cond_match('=');
                 self.add_token_wo_literal(if matched_equal {
@@ -126,20 +191,24 @@ impl<'a> Scanner<'a> {
                 });
             }
             '<' => {
-                let matched_equal: bool = self.cond_match('=');
-                self.add_token_wo_literal(if matched_equal {
+                let ttype = if self.cond_match('=') {
                     TokenType::LessEqual
+                } else if self.cond_match('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
-                });
+                };
+                self.add_token_wo_literal(ttype);
             }
             '>' => {
-                let matched_equal: bool = self.cond_match('=');
-                self.add_token_wo_literal(if matched_equal {
+                let ttype = if self.cond_match('=') {
                     TokenType::GreaterEqual
+                } else if self.cond_match('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
-                });
+                };
+                self.add_token_wo_literal(ttype);
             }
             '/' => {
                 let matched_slash: bool = self.cond_match('/');
@@ -147,12 +216,14 @@ impl<'a> Scanner<'a> {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.cond_match('=') {
+                    self.add_token_wo_literal(TokenType::SlashEqual);
                 } else {
                     self.add_token_wo_literal(TokenType::Slash);
                 }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             '"' => self.process_string(),
             c if c.is_ascii_digit() => self.process_number(),
             c if c == 'o' && self.cond_match('r') => {
@@ -176,11 +247,22 @@ impl<'a> Scanner<'a> {
     }
     pub fn scan_tokens(&mut self) {
         while !self.is_at_end() {
-            self.start = self.current;
+            self.mark_start();
             self.scan_token();
         }
+        self.mark_start();
         self.add_token_wo_literal(TokenType::Eof);
     }
+    fn mark_start(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_col = self.start - self.line_start + 1;
+    }
+    // Call after consuming a '\n'.
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
     fn is_at_end(&self) -> bool {
         self.current == self.source.len()
     }
@@ -196,10 +278,9 @@ impl<'a> Scanner<'a> {
     }
     fn process_string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
         if self.is_at_end() {
             self.error_handler.error(self.line, "Unterminated string.");
@@ -261,11 +342,21 @@ impl ErrorHandler {
     pub fn error(&mut self, line: usize, message: &str) {
         self.report(line, "", message);
     }
+    pub fn token_error(&mut self, token: &Token, message: &str) {
+        if token.ttype == TokenType::Eof {
+            self.report(token.line, " at end", message);
+        } else {
+            self.report(token.line, &format!(" at '{}'", token.lexeme), message);
+        }
+    }
     fn report(&mut self, line: usize, loc: &str, message: &str) {
-        eprintln!("[line {line}] Error {loc}: {message}.");
+        let error = format!("[line {line}] Error{loc}: {message}");
+        eprintln!("{error}");
+        self.errors.push(error);
         self.has_error = true;
     }
     pub fn reset(&mut self) {
         self.has_error = false;
+        self.errors.clear();
     }
 }
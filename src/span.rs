@@ -0,0 +1,112 @@
+use crate::ast::Expr;
+use crate::scanner::Token;
+
+// Where a node comes from in the source. Lines and columns start from 1,
+// the end is exclusive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl Span {
+    pub fn from_token(token: &Token) -> Span {
+        // Strings can span multiple lines.
+        let (end_line, end_col) = match token.lexeme.rsplit_once('\n') {
+            Some((_, last_line)) => (
+                token.line + token.lexeme.matches('\n').count(),
+                last_line.chars().count() + 1,
+            ),
+            None => (token.line, token.col + token.lexeme.chars().count()),
+        };
+        Span {
+            line: token.line,
+            col: token.col,
+            end_line,
+            end_col,
+        }
+    }
+    // Span covering both self and the other span which comes later in the source.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            line: self.line,
+            col: self.col,
+            end_line: other.end_line,
+            end_col: other.end_col,
+        }
+    }
+    // The operator of `++xs[0]` comes before the target, its value is made up by the parser.
+    pub fn index_set(object: Span, bracket: &Token, operator: &Token, value: Span) -> Span {
+        let target = object.to(Span::from_token(bracket));
+        if (operator.line, operator.col) < (object.line, object.col) {
+            Span::from_token(operator).to(target)
+        } else {
+            object.to(value)
+        }
+    }
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary { left, right, .. } => left.span().to(right.span()),
+            Expr::Conditional {
+                condition,
+                else_branch,
+                ..
+            } => condition.span().to(else_branch.span()),
+            Expr::Grouping { span, .. } => *span,
+            Expr::Index {
+                object, bracket, ..
+            } => object.span().to(Span::from_token(bracket)),
+            Expr::IndexSet {
+                object,
+                bracket,
+                operator,
+                value,
+                ..
+            } => Span::index_set(object.span(), bracket, operator, value.span()),
+            Expr::List { span, .. } => *span,
+            Expr::Literal { span, .. } => *span,
+            Expr::Map { span, .. } => *span,
+            Expr::Unary { operator, right } => Span::from_token(operator).to(right.span()),
+            Expr::Variable { name } => Span::from_token(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::LiteralType;
+    use crate::types::TokenType;
+
+    #[test]
+    fn test_multiline_token_span() {
+        let token = Token::new(
+            TokenType::String,
+            "\"a\nbc\"".to_string(),
+            LiteralType::Str("a\nbc".to_string()),
+            3,
+            5,
+        );
+        let expected = Span {
+            line: 3,
+            col: 5,
+            end_line: 4,
+            end_col: 4,
+        };
+        assert_eq!(Span::from_token(&token), expected);
+    }
+
+    #[test]
+    fn test_increment_spans() {
+        for source in ["++xs[0]", "xs[0]++", "xs[0] += 1"] {
+            let expr = crate::parse(source, &mut crate::scanner::ErrorHandler::new()).unwrap();
+            let span = expr.span();
+            assert_eq!((span.col, span.end_col), (1, source.len() + 1), "{source}");
+        }
+    }
+}
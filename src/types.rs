@@ -1,8 +1,6 @@
 use std::collections::HashMap;
 use std::sync::LazyLock;
 
-use crate::scanner::Token;
-
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 // I don't like this, but we have TokenType and LiteralType that somewhat intersect.
 pub enum TokenType {
@@ -11,6 +9,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -18,6 +18,13 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Question,
+    Colon,
 
     // One or two character tokens.
     Bang,
@@ -26,8 +33,17 @@ pub enum TokenType {
     EqualEqual,
     Greater,
     GreaterEqual,
+    GreaterGreater,
     Less,
     LessEqual,
+    LessLess,
+    MinusEqual,
+    MinusMinus,
+    PlusEqual,
+    PlusPlus,
+    SlashEqual,
+    StarEqual,
+    StarStar,
 
     // Literals.
     Identifier,
@@ -36,9 +52,14 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
+    Div,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
@@ -48,7 +69,9 @@ pub enum TokenType {
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -58,9 +81,14 @@ pub enum TokenType {
 pub static KEYWORDS: LazyLock<HashMap<String, TokenType>> = LazyLock::new(|| {
     let mut m = HashMap::new();
     m.insert("and".to_string(), TokenType::And);
+    m.insert("break".to_string(), TokenType::Break);
+    m.insert("catch".to_string(), TokenType::Catch);
     m.insert("class".to_string(), TokenType::Class);
+    m.insert("continue".to_string(), TokenType::Continue);
+    m.insert("div".to_string(), TokenType::Div);
     m.insert("else".to_string(), TokenType::Else);
     m.insert("false".to_string(), TokenType::False);
+    m.insert("finally".to_string(), TokenType::Finally);
     m.insert("for".to_string(), TokenType::For);
     m.insert("fun".to_string(), TokenType::Fun);
     m.insert("if".to_string(), TokenType::If);
@@ -70,7 +98,9 @@ pub static KEYWORDS: LazyLock<HashMap<String, TokenType>> = LazyLock::new(|| {
     m.insert("return".to_string(), TokenType::Return);
     m.insert("super".to_string(), TokenType::Super);
     m.insert("this".to_string(), TokenType::This);
+    m.insert("throw".to_string(), TokenType::Throw);
     m.insert("true".to_string(), TokenType::True);
+    m.insert("try".to_string(), TokenType::Try);
     m.insert("var".to_string(), TokenType::Var);
     m.insert("while".to_string(), TokenType::While);
     m
@@ -0,0 +1,105 @@
+// Values of Lox programs at runtime.
+//
+// Lists are shared and mutable: `ys = xs; push(ys, 1);` changes `xs` too.
+// Errors are values natives return for failures scripts should handle, e.g. a missing file.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::scanner::{LiteralType, Numeric};
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Num(Numeric),
+    Str(String),
+    List(Rc<RefCell<Vec<Value>>>),
+    Error(String),
+}
+
+impl Value {
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    // Name of the type in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Num(_) => "number",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Error(_) => "error",
+        }
+    }
+}
+
+impl From<LiteralType> for Value {
+    fn from(literal: LiteralType) -> Value {
+        match literal {
+            LiteralType::Str(s) => Value::Str(s),
+            LiteralType::Num(n) => Value::Num(n),
+            LiteralType::Bool(b) => Value::Bool(b),
+            LiteralType::Null => Value::Nil,
+        }
+    }
+}
+
+// What `print` shows. Strings are quoted inside lists, so that `["a, b"]` and `["a", "b"]` differ.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Num(Numeric::Integer(n)) => write!(f, "{n}"),
+            Value::Num(Numeric::Float(n)) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match value {
+                        Value::Str(s) => write!(f, "{s:?}")?,
+                        value => write!(f, "{value}")?,
+                    }
+                }
+                write!(f, "]")
+            }
+            Value::Error(message) => write!(f, "error: {message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let list = Value::list(vec![
+            Value::Num(Numeric::Float(1.0)),
+            Value::Num(Numeric::Float(2.5)),
+            Value::Str("a, b".to_string()),
+            Value::Nil,
+            Value::list(vec![Value::Bool(true)]),
+        ]);
+        assert_eq!(list.to_string(), "[1, 2.5, \"a, b\", nil, [true]]");
+        assert_eq!(Value::Str("a".to_string()).to_string(), "a");
+        assert_eq!(Value::from(LiteralType::Null).to_string(), "nil");
+    }
+
+    #[test]
+    fn test_lists_are_shared() {
+        let xs = Value::list(Vec::new());
+        let ys = xs.clone();
+        if let Value::List(values) = &ys {
+            values.borrow_mut().push(Value::Nil);
+        }
+        assert_eq!(xs.to_string(), "[nil]");
+    }
+}
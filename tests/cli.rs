@@ -0,0 +1,117 @@
+// Runs the tlox binary the way people do.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn tlox(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tlox_bin"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn temp_script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("tlox-cli-{}-{name}", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_run() {
+    let script = temp_script("run.lox", "1 + 2 * 3");
+    let output = tlox(&[&script, "arg"], "");
+    assert_eq!(stdout(&output), "(+ 1 (* 2 3))\n");
+    assert!(output.status.success());
+    let output = tlox(&["run", &script], "");
+    assert_eq!(stdout(&output), "(+ 1 (* 2 3))\n");
+    std::fs::remove_file(script).unwrap();
+}
+
+#[test]
+fn test_inline_code_and_stdin() {
+    assert_eq!(stdout(&tlox(&["-e", "1 + 2"], "")), "(+ 1 2)\n");
+    assert_eq!(stdout(&tlox(&["run", "-"], "-x")), "(- x)\n");
+    assert_eq!(
+        stdout(&tlox(&["ast", "--format=sexpr", "-"], "a[0]")),
+        "(index a 0)\n"
+    );
+}
+
+#[test]
+fn test_check() {
+    let output = tlox(&["check", "-e", "1 + 2"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    let output = tlox(&["check", "-"], "1 +");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+    assert!(
+        stderr(&output).contains("Error at end"),
+        "{}",
+        stderr(&output)
+    );
+    for trailing in ["1 2", "1 )"] {
+        let output = tlox(&["check", "-e", trailing], "");
+        assert_eq!(output.status.code(), Some(65), "{trailing}");
+        assert!(stderr(&output).contains("Expect end of expression."));
+    }
+}
+
+#[test]
+fn test_tokens() {
+    let output = tlox(&["tokens", "-e", "1"], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Number"), "{}", stdout(&output));
+    let json = stdout(&tlox(&["tokens", "--format=json", "-e", "1"], ""));
+    assert!(json.starts_with('['), "{json}");
+    // Same as the options from before the commands.
+    assert_eq!(stdout(&tlox(&["--tokens=json", "-e", "1"], "")), json);
+    let ast = tlox(&["--ast=sexpr", "-e", "1 + 2"], "");
+    assert_eq!(stdout(&ast), "(+ 1 2)\n");
+}
+
+#[test]
+fn test_repl() {
+    let output = tlox(&["repl"], "1 + 2\n\n");
+    assert_eq!(stdout(&output), "> (+ 1 2)\n> ");
+}
+
+#[test]
+fn test_help_and_version() {
+    let output = tlox(&["--help"], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("Usage: tlox"));
+    let output = tlox(&["-V"], "");
+    assert_eq!(
+        stdout(&output),
+        format!("tlox {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn test_errors() {
+    let output = tlox(&["--bogus"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(stderr(&output).starts_with("Unknown option '--bogus'."));
+    let output = tlox(&["no/such/script.lox"], "");
+    assert_eq!(output.status.code(), Some(66));
+    assert!(stderr(&output).starts_with("Could not read no/such/script.lox"));
+}